@@ -1,12 +1,13 @@
 use crate::algorithms::raptor::visualiser::visualise_earliest_arrivals;
-use crate::database::queries::{count_stop_times, get_parent_station_map, get_stop_times, get_transfer_times};
+use crate::database::queries::{count_stop_times, get_parent_station_map, get_stop_times, get_transfer_times, get_trip_route_info_map, get_wheelchair_stops, TripRouteInfo};
 use crate::getters::get_stop_readable;
+use crate::reliability::RiskLevel;
 use crate::types::JourneyPart;
 use crate::utils::{deserialize_from_disk,serialize_to_disk};
 use indicatif::{ProgressBar, ProgressStyle};
 use rbatis::executor::Executor;
 use rbatis::RBatis;
-use reisplanner_gtfs::gtfs::types::{Route, Trip};
+use reisplanner_gtfs::gtfs::types::{AllowedType, Route, RouteType, Trip};
 use serde::{Deserialize, Serialize};
 use std::cmp::min;
 use std::collections::{HashMap, HashSet};
@@ -17,6 +18,12 @@ use tracing::{debug, error};
 use crate::algorithms::raptor::Mode::NotApplicable;
 
 mod visualiser;
+pub mod options;
+pub mod alternatives;
+pub mod isochrone;
+pub mod matrix;
+
+pub use options::{QueryOptions, ViaStop};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Copy, Hash)]
 pub struct Location {
@@ -32,6 +39,37 @@ pub struct TripLeg {
     pub departure: u32,
     pub arrival: u32,
     pub trip_id: u32,
+    /// Key of the `RRoute` of this trip in the timetable
+    pub route_id: u32,
+    pub wheelchair: Accessibility,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TransferLeg {
+    pub from_stop: u32,
+    pub to_stop: u32,
+    pub duration: u32,
+    /// Both the platform we arrive at and the one we depart from are wheelchair accessible
+    pub wheelchair_accessible: bool,
+    /// Risk of missing the next trip, see `reliability::annotate_reliability`
+    pub risk: RiskLevel,
+}
+
+/// Wheelchair accessibility of a single trip leg
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Accessibility {
+    /// The vehicle is wheelchair accessible
+    pub trip: bool,
+    /// The stop where we board is wheelchair accessible
+    pub boarding: bool,
+    /// The stop where we alight is wheelchair accessible
+    pub alighting: bool,
+}
+
+impl Accessibility {
+    pub fn is_accessible(&self) -> bool {
+        self.trip && self.boarding && self.alighting
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -43,12 +81,15 @@ pub struct Arrival {
     departure_stop: Option<Location>,
     departure_time: Option<u32>,
     mode: Mode,
+    /// Total time (in seconds) spent transferring in the journey up to here
+    transfer_time: u32,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub enum Mode {
     NotApplicable,
-    Trip(u32),
+    /// Arrived using a trip in the route with `route_id` in the timetable
+    Trip { trip_id: u32, route_id: u32 },
     Transfer,
 }
 
@@ -60,16 +101,16 @@ pub struct Transfer {
 }
 
 impl Arrival {
-    pub fn new_trip(time: u32, stop: Location, departure_time: u32, departure_stop: Location, trip_id: u32) -> Self {
-        Self { time, stop, departure_stop: Some(departure_stop), departure_time: Some(departure_time), mode: Mode::Trip(trip_id) }
+    pub fn new_trip(time: u32, stop: Location, departure_time: u32, departure_stop: Location, trip_id: u32, route_id: u32, transfer_time: u32) -> Self {
+        Self { time, stop, departure_stop: Some(departure_stop), departure_time: Some(departure_time), mode: Mode::Trip { trip_id, route_id }, transfer_time }
     }
 
-    pub fn new_transfer(time: u32, stop: Location, departure_time: u32, departure_stop: Location) -> Self {
-        Self { time, stop, departure_stop: Some(departure_stop), departure_time: Some(departure_time), mode: Mode::Transfer }
+    pub fn new_transfer(time: u32, stop: Location, departure_time: u32, departure_stop: Location, transfer_time: u32) -> Self {
+        Self { time, stop, departure_stop: Some(departure_stop), departure_time: Some(departure_time), mode: Mode::Transfer, transfer_time }
     }
 
     pub fn departure_station(time: u32, stop: Location) -> Self {
-        Self { time, stop, departure_stop: None, departure_time: None, mode: NotApplicable }
+        Self { time, stop, departure_stop: None, departure_time: None, mode: NotApplicable, transfer_time: 0 }
     }
 }
 
@@ -98,24 +139,63 @@ impl Connection {
     }
 }
 
+/// Properties of a single trip in a `RRoute` that queries can filter on
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct TripAttributes {
+    pub trip_id: u32,
+    /// `Trip.service_id`, the dates on which the trip runs
+    pub service_id: u32,
+    pub wheelchair_accessible: AllowedType,
+    pub bikes_allowed: AllowedType,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
 /// Note: while this is the same concept as a GTFS route, they are not equal.
 /// In the OvApi GTFS set, most routes only have one trip.
 /// In order to efficiently use Raptor, trips with the same stops should be
 /// combined in the same routes.
+/// Trips are only combined if they also share the route type and operator,
+/// so that queries can filter on those.
 pub struct RRoute {
     stops: Vec<Location>,
     connections: Vec<Vec<Connection>>,
+    /// Attributes of each trip, in the same order as `connections`
+    trips: Vec<TripAttributes>,
+    route_type: RouteType,
+    /// `Route.agency_id` of the trips in this route
+    agency_id: String,
+    /// The stop_ids in this route where boarding with a wheelchair is possible
+    wheelchair_stops: HashSet<u32>,
 }
 
 impl RRoute {
-    pub fn new(stops: Vec<Location>) -> Self {
+    pub fn new(stops: Vec<Location>, route_type: RouteType, agency_id: String) -> Self {
         Self {
             stops,
             connections: Vec::new(),
+            trips: Vec::new(),
+            route_type,
+            agency_id,
+            wheelchair_stops: HashSet::new(),
         }
     }
 
+    pub fn trip_attributes(&self, trip_id: u32) -> Option<&TripAttributes> {
+        self.trips.iter().find(|t| t.trip_id == trip_id)
+    }
+
+    pub fn is_wheelchair_accessible(&self, stop_id: u32) -> bool {
+        self.wheelchair_stops.contains(&stop_id)
+    }
+
+    pub fn route_type(&self) -> RouteType {
+        self.route_type
+    }
+
+    pub fn agency_id(&self) -> &str {
+        &self.agency_id
+    }
+
     pub fn is_before(&self, p1: u32, p2: u32) -> bool {
         for stop in &self.stops {
             if stop.parent_id == p1 {
@@ -142,18 +222,22 @@ impl RRoute {
         panic!("Stop {p} not in this route")
     }
 
-    pub fn trip_from_station(&self, parent_id: u32, start_time: u32) -> Option<&[Connection]> {
+    pub fn trip_from_station(&self, parent_id: u32, start_time: u32, options: &QueryOptions) -> Option<&[Connection]> {
         let index = self.stops.iter().enumerate()
             .find(|&(_, stop)| stop.parent_id == parent_id)
             .map(|(i, _)| i)
             .unwrap_or_else(|| panic!("Stop {parent_id} not in this route"));
 
-        self.trip_from(index, start_time)
+        self.trip_from(index, start_time, options)
     }
 
-    pub fn trip_from(&self, stop_index: usize, start_time: u32) -> Option<&[Connection]> {
-        for trips in &self.connections {
-            if trips[stop_index].departure >= start_time {
+    /// Get the first trip that departs from this stop at or after `start_time`
+    /// and that is allowed by the query options
+    pub fn trip_from(&self, stop_index: usize, start_time: u32, options: &QueryOptions) -> Option<&[Connection]> {
+        for (trips, attributes) in self.connections.iter().zip(&self.trips) {
+            if trips[stop_index].departure >= start_time
+                && options.allows_trip(attributes)
+                && options.allows_connection(&trips[stop_index]) {
                 return Some(&trips[stop_index..]);
             }
         }
@@ -161,6 +245,14 @@ impl RRoute {
         None
     }
 
+    /// Sort the trips by departure time, so that `trip_from` finds the earliest trip
+    fn sort_trips(&mut self) {
+        let mut order: Vec<_> = (0..self.connections.len()).collect();
+        order.sort_by_key(|&i| self.connections[i].first().map(|c| c.departure));
+        self.connections = order.iter().map(|&i| self.connections[i].clone()).collect();
+        self.trips = order.iter().map(|&i| self.trips[i].clone()).collect();
+    }
+
     pub fn contains_station(&self, stop_id: &u32) -> bool {
         self.stops.iter().map(|l| l.parent_id)
             .any(|s| s == *stop_id)
@@ -169,13 +261,52 @@ impl RRoute {
 
 const PAGE_SIZE: u64 = 1_000_000;
 
+type RouteKey = (Vec<Location>, RouteType, String);
+
+/// Add a trip to the route with the same stops, route type and operator
+fn add_trip(
+    routes: &mut HashMap<RouteKey, RRoute>,
+    stops: Vec<Location>,
+    connections: Vec<Connection>,
+    trip_routes: &HashMap<u32, TripRouteInfo>,
+    wheelchair_stops: &HashSet<u32>,
+) -> anyhow::Result<()> {
+    let Some(trip_id) = connections.first().map(|c| c.trip_id) else {
+        return Ok(());
+    };
+    let info = trip_routes.get(&trip_id)
+        .ok_or(anyhow::Error::msg("Route of trip not found"))?;
+
+    // TODO clone
+    let route = routes.entry((stops.clone(), info.route_type, info.agency_id.clone()))
+        .or_insert_with(|| RRoute::new(stops, info.route_type, info.agency_id.clone()));
+    // Trips in the same route can end at a different stop, so check all stops of the trip
+    for connection in &connections {
+        for stop in [connection.departure_station, connection.arrival_station] {
+            if wheelchair_stops.contains(&stop.stop_id) {
+                route.wheelchair_stops.insert(stop.stop_id);
+            }
+        }
+    }
+    route.trips.push(TripAttributes {
+        trip_id,
+        service_id: info.service_id,
+        wheelchair_accessible: info.wheelchair_accessible.unwrap_or_default(),
+        bikes_allowed: info.bikes_allowed.unwrap_or_default(),
+    });
+    route.connections.push(connections);
+    Ok(())
+}
+
 async fn generate_timetable(db: &RBatis) -> anyhow::Result<HashMap<u32, RRoute>> {
     debug!("Getting trips, routes and stations...");
     let parent_stations = get_parent_station_map(db).await?;
+    let trip_routes = get_trip_route_info_map(db).await?;
+    let wheelchair_stops = get_wheelchair_stops(db).await?;
     let total_count = count_stop_times(db).await?;
 
     debug("Generating timetable from stop_times, this will take a while...");
-    let mut routes = HashMap::new(); // [stop_id] -> RRoute
+    let mut routes = HashMap::new(); // [stop_ids, route_type, agency_id] -> RRoute
     let bar = ProgressBar::new(total_count);
     bar.set_style(ProgressStyle::default_bar()
         .template("{wide_bar} Elapsed: {elapsed_precise}, ETA: {eta_precise}")?);
@@ -212,10 +343,7 @@ async fn generate_timetable(db: &RBatis) -> anyhow::Result<HashMap<u32, RRoute>>
                     // Reset temporary storages and write values to result
                     // This if statement does not trigger at the very last iteration
                     // That's why we also write this at count != PAGE_SIZE below.
-                    // TODO clone
-                    let route = routes.entry(current_trip_stops.clone())
-                        .or_insert(RRoute::new(current_trip_stops));
-                    route.connections.push(current_trip_connections);
+                    add_trip(&mut routes, current_trip_stops, current_trip_connections, &trip_routes, &wheelchair_stops)?;
 
                     current_trip_stops = Vec::new();
                     current_trip_connections = Vec::new();
@@ -225,9 +353,7 @@ async fn generate_timetable(db: &RBatis) -> anyhow::Result<HashMap<u32, RRoute>>
 
         bar.inc(count as u64);
         if count != PAGE_SIZE as usize {
-            let route = routes.entry(current_trip_stops.clone())
-                .or_insert(RRoute::new(current_trip_stops));
-            route.connections.push(current_trip_connections);
+            add_trip(&mut routes, current_trip_stops, current_trip_connections, &trip_routes, &wheelchair_stops)?;
             break;
         } else {
             highest_id = stop_times.last().unwrap().id.unwrap();
@@ -238,7 +364,10 @@ async fn generate_timetable(db: &RBatis) -> anyhow::Result<HashMap<u32, RRoute>>
     // Change key type to be an integer
     let routes = routes.into_iter()
         .enumerate()
-        .map(|(i, (_, v))| { (i as u32, v) })
+        .map(|(i, (_, mut v))| {
+            v.sort_trips();
+            (i as u32, v)
+        })
         .collect();
 
     Ok(routes)
@@ -249,7 +378,9 @@ pub async fn generate_transfer_times(db: &RBatis) -> anyhow::Result<HashMap<u32,
     Ok(transfers)
 }
 
-const TIMETABLE: &str = "raptor_timetable.blob";
+/// Cache of the timetable. Change the version when the layout of `RRoute` changes,
+/// so that a cache written by an older version is regenerated instead of misread.
+const TIMETABLE: &str = "raptor_timetable.v3.blob";
 
 // TODO make generic
 pub async fn get_timetable(db: &RBatis, cache: bool) -> anyhow::Result<HashMap<u32, RRoute>> {
@@ -259,7 +390,8 @@ pub async fn get_timetable(db: &RBatis, cache: bool) -> anyhow::Result<HashMap<u
         // Get timetable from disk or generate
         match deserialize_from_disk(TIMETABLE) {
             Ok(timetable) => Ok(timetable),
-            Err(_) => {
+            Err(e) => {
+                debug!("Could not read {TIMETABLE} ({e}), generating the timetable");
                 let timetable = generate_timetable(db).await?;
                 serialize_to_disk(&timetable, TIMETABLE)?;
                 Ok(timetable)
@@ -277,9 +409,36 @@ pub async fn run_raptor<'a>(
     departure_time: impl Into<u32>,
     timetable: &'a HashMap<u32, RRoute>,
     transfer_times: &'a HashMap<u32, u32>,
+    options: &QueryOptions,
     db: &impl Executor,
 ) -> anyhow::Result<Option<Vec<JourneyPart>>> {
-    let departure_time = departure_time.into();
+    let tau_star = earliest_arrivals(
+        departure_stop, Some(arrival_stop), departure_time.into(), u32::MAX,
+        timetable, transfer_times, options, db,
+    ).await?;
+
+    if !tau_star.contains_key(&arrival_stop) {
+        return Ok(None);
+    }
+
+    Ok(Some(construct_path(&tau_star, departure_stop, arrival_stop, timetable)))
+}
+
+/// Run the RAPTOR rounds from `departure_stop` and return the earliest arrival
+/// at every reached stop (τ∗), keyed by parent id (trips) or stop id (transfers).
+/// If `arrival_stop` is given, target pruning is applied for this stop.
+/// Arrivals after `max_arrival` are pruned as well.
+#[allow(clippy::too_many_arguments)]
+pub async fn earliest_arrivals<'a>(
+    departure_stop: u32,
+    arrival_stop: Option<u32>,
+    departure_time: u32,
+    max_arrival: u32,
+    timetable: &'a HashMap<u32, RRoute>,
+    transfer_times: &'a HashMap<u32, u32>,
+    options: &QueryOptions,
+    db: &impl Executor,
+) -> anyhow::Result<HashMap<u32, Arrival>> {
     let departure_location = Location { stop_id: departure_stop, parent_id: departure_stop };
 
     // The algorithm associates with each stop p a multilabel (τ0(p), τ1(p), ..  , τK (p)),
@@ -292,7 +451,7 @@ pub async fn run_raptor<'a>(
     // representing the earliest known arrival time at pi.
     let mut tau_star = tau_k[0].clone();
 
-    // Map to keep the found transfers to each trip/stop
+    // Map to keep the found transfers to each (parent_id, route_id)
     let mut transfers = HashMap::new();
 
     let mut marked = HashSet::new();
@@ -324,6 +483,10 @@ pub async fn run_raptor<'a>(
                 if !r.contains_station(&p.parent_id) {
                     continue;
                 }
+                // Skip routes with a type or operator that the query does not allow
+                if !options.allows_route(r) || options.excluded_routes.contains(&route_id) {
+                    continue;
+                }
 
                 // Moreover, since the marked stops are exactly those where we potentially
                 // “hop on” a trip in round k, we only have to traverse a route beginning
@@ -352,8 +515,17 @@ pub async fn run_raptor<'a>(
 
             // t ← ⊥ // the current trip
             let mut t: Option<&[Connection]> = None;
+            // The index in r.stops where we boarded t, t starts at this stop
+            let mut boarded_at = 0;
+            // The total transfer time in the journey when we boarded t
+            let mut boarded_transfer_time = 0;
 
             for (i, p_i) in r.stops.iter().enumerate() {
+                // We cannot board, alight or pass through avoided stops
+                if options.avoids(p_i) {
+                    t = None;
+                    continue;
+                }
                 // Let et(r, pi) be the earliest trip in route r that one can catch at stop pi,
                 // i. e., the earliest trip t such that τdep(t, pi) ≥ τk−1(pi).
                 // (Note that this trip may not exist, in which case et(r, pi) is undefined.)
@@ -364,20 +536,41 @@ pub async fn run_raptor<'a>(
                 // earlier trip (because a quicker path to pi has been found
                 // in a previous round). Thus, we have to check if
                 // τk−1(pi) < τarr(t, pi) and update t by recomputing et(r, pi).
-                if let Some(possible_arrival) = tau_k[k - 1].get(&p_i.parent_id) {
-                    if let Some(new_trip) = r.trip_from_station(p_i.parent_id, possible_arrival.time) {
-                        // Determine transfer information (if we are on a trip, so not for departure)
-                        let transfer_time = if t.is_some() {
-                            transfer_times.get(&p_i.stop_id).map(|t| t * 60)
-                        } else { None };
-                        if t.is_none() || (i < t.unwrap().len() && possible_arrival.time + transfer_time.unwrap() < t.unwrap()[i].departure) {
-                            if let Some(transfer_time) = transfer_time {
-                                // Save transfer here (using stop_id (= platform) instead of parent_id)
-                                // Note: possible_arrival = the arrival from the trip using which we  are here earliest.
-                                //  and: new_trip[0] = the connection which we are transferring to
-                                transfers.insert(possible_arrival.stop.parent_id, Transfer{duration: transfer_time, from_stop: possible_arrival.stop});
+                let boarding_label = tau_k[k - 1].get(&p_i.parent_id)
+                    .filter(|_| options.allows_stop(r, p_i));
+                if let Some(possible_arrival) = boarding_label {
+                    // Determine transfer information (if we arrived here using a trip, so not for departure)
+                    // Note: possible_arrival = the arrival from the trip using which we are here earliest.
+                    let transfer = match possible_arrival.mode {
+                        Mode::Trip { route_id: from_route_id, .. } => {
+                            let station_time = transfer_times.get(&p_i.stop_id)
+                                .map(|t| t * 60).unwrap_or(0);
+                            let modes = timetable.get(&from_route_id)
+                                .map(|from_route| (from_route.route_type, r.route_type));
+                            Some(Transfer {
+                                duration: options.transfer_duration(station_time, modes),
+                                from_stop: possible_arrival.stop,
+                            })
+                        }
+                        _ => None,
+                    };
+                    let transfer_duration = transfer.map(|t| t.duration).unwrap_or(0);
+                    let transfer_total = possible_arrival.transfer_time + transfer_duration;
+
+                    let new_trip = r.trip_from(i, possible_arrival.time + transfer_duration, options)
+                        .filter(|_| options.allows_transfer_time(transfer_total));
+                    if let Some(new_trip) = new_trip {
+                        let current = t.and_then(|t| t.get(i - boarded_at));
+                        if current.is_none_or(|c| new_trip[0].departure < c.departure) {
+                            // Save transfer here (using stop_id (= platform) instead of parent_id)
+                            // Note: new_trip[0] = the connection which we are transferring to
+                            match transfer {
+                                Some(transfer) => { transfers.insert((p_i.parent_id, route_id), transfer); }
+                                None => { transfers.remove(&(p_i.parent_id, route_id)); }
                             }
                             t = Some(new_trip);
+                            boarded_at = i;
+                            boarded_transfer_time = transfer_total;
                         }
                     }
                 }
@@ -385,29 +578,38 @@ pub async fn run_raptor<'a>(
                 // For each subsequent stop pj, we can update τk(pj) using
                 // this trip. To reconstruct the journey, we set a parent
                 // pointer to the stop at which t was boarded.
-                if let Some(t) = t {
+                if let Some(trip) = t {
                     // p_h = the stop at which we "hop on" the trip
-                    let p_h = &t[0].departure_station;
-                    let departure = t[0].departure;
-
-                    if let Some(connection) = t.get(i) {
+                    let p_h = &trip[0].departure_station;
+                    let departure = trip[0].departure;
+
+                    if let Some(connection) = trip.get(i - boarded_at) {
+                        // We have to leave the trip if we may not stay on board,
+                        // e.g. with a bicycle during rush hour
+                        if !options.allows_connection(connection) {
+                            t = None;
+                            continue;
+                        }
                         let p_j = connection.arrival_station;
                         let earliest_at_pj = tau_star.get(&p_j.parent_id)
                             .map(|a| a.time).unwrap_or(u32::MAX);
-                        let earliest_at_arr = tau_star.get(&arrival_stop)
+                        let earliest_at_arr = arrival_stop.and_then(|s| tau_star.get(&s))
                             .map(|a| a.time).unwrap_or(u32::MAX);
-                        if connection.arrival < min(earliest_at_pj, earliest_at_arr) {
-                            if let Some(transfer) = transfers.get(&p_h.parent_id) {
+                        if connection.arrival < min(earliest_at_pj, earliest_at_arr)
+                            && connection.arrival <= max_arrival
+                            && options.allows_stop(r, &p_j) {
+                            if let Some(transfer) = transfers.get(&(p_h.parent_id, route_id)) {
                                 let transfer_arrival = Arrival::new_transfer(
                                     departure + transfer.duration, *p_h,
-                                    departure, transfer.from_stop,
+                                    departure, transfer.from_stop, boarded_transfer_time,
                                 );
                                 tau_k[k].insert(p_h.stop_id, transfer_arrival);
                                 tau_star.insert(p_h.stop_id, transfer_arrival);
                             }
                             let arrival = Arrival::new_trip(
                                 connection.arrival, p_j,
-                                departure, *p_h, connection.trip_id,
+                                departure, *p_h, connection.trip_id, route_id,
+                                boarded_transfer_time,
                             );
                             tau_k[k].insert(p_j.parent_id, arrival);
                             tau_star.insert(p_j.parent_id, arrival);
@@ -419,13 +621,25 @@ pub async fn run_raptor<'a>(
         }
         // TODO document this feature
         if env::var("SHOW_DOTS").is_ok_and(|v|v == "1") {
-            visualise_earliest_arrivals(&tau_star, k, arrival_stop, db).await?;
+            if let Some(arrival_stop) = arrival_stop {
+                visualise_earliest_arrivals(&tau_star, k, arrival_stop, db).await?;
+            }
         }
 
         // If no new stops are marked, the route cannot be improved
         if marked.is_empty() { break; }
     }
 
+    Ok(tau_star)
+}
+
+/// Construct the journey to `arrival_stop` from the earliest arrivals
+fn construct_path(
+    tau_star: &HashMap<u32, Arrival>,
+    departure_stop: u32,
+    arrival_stop: u32,
+    timetable: &HashMap<u32, RRoute>,
+) -> Vec<JourneyPart> {
     // Construct the path, starting at the arrival
     let mut path = Vec::new();
     let mut current_stop = arrival_stop;
@@ -433,24 +647,37 @@ pub async fn run_raptor<'a>(
     while let Some(arrival) = tau_star.get(&current_stop) {
         // path.push(JourneyPart::Station(arrival.stop));
         match arrival.mode {
-            Mode::Trip(trip_id) => {
+            Mode::Trip { trip_id, route_id } => {
                 // Assume that departure_stop and departure_time are Some, if mode is Trip
+                let from_stop = arrival.departure_stop.unwrap();
+                let route = timetable.get(&route_id).expect("Route id of arrival should be in timetable");
+                let wheelchair = Accessibility {
+                    trip: route.trip_attributes(trip_id)
+                        .is_some_and(|t| t.wheelchair_accessible == AllowedType::Allowed),
+                    boarding: route.is_wheelchair_accessible(from_stop.stop_id),
+                    alighting: route.is_wheelchair_accessible(arrival.stop.stop_id),
+                };
                 let leg = TripLeg {
-                    from_stop: arrival.departure_stop.unwrap(),
+                    from_stop,
                     to_stop: arrival.stop,
                     departure: arrival.departure_time.unwrap(),
                     arrival: arrival.time,
                     trip_id,
+                    route_id,
+                    wheelchair,
                 };
                 path.push(JourneyPart::Vehicle(leg));
             }
             Mode::Transfer => {
                 // Assume that departure_stop and departure_time are Some, if mode is Transfer
-                path.push(JourneyPart::Transfer(
-                    arrival.departure_stop.unwrap().stop_id,
-                    arrival.stop.stop_id,
-                    arrival.time - arrival.departure_time.unwrap(),
-                ));
+                // Accessibility is determined from the surrounding trip legs below
+                path.push(JourneyPart::Transfer(TransferLeg {
+                    from_stop: arrival.departure_stop.unwrap().stop_id,
+                    to_stop: arrival.stop.stop_id,
+                    duration: arrival.time - arrival.departure_time.unwrap(),
+                    wheelchair_accessible: false,
+                    risk: RiskLevel::Unknown,
+                }));
             }
             NotApplicable => {}
         }
@@ -458,7 +685,8 @@ pub async fn run_raptor<'a>(
             // First try stop_id to find the "transfer" connection
             current_stop = stop.stop_id;
             // If there is no associated arrival, get the parent stop, for the "vehicle" connection
-            if !tau_star.contains_key(&current_stop) {
+            // A transfer always starts at the "vehicle" connection, which may be at the same platform
+            if !tau_star.contains_key(&current_stop) || arrival.mode == Mode::Transfer {
                 current_stop = stop.parent_id;
             }
             let old_length = seen.len();
@@ -477,6 +705,79 @@ pub async fn run_raptor<'a>(
 
     path.reverse();
 
+    // A transfer is accessible if we can alight from the previous and board the next trip
+    for i in 0..path.len() {
+        let alighting = match i.checked_sub(1).and_then(|i| path.get(i)) {
+            Some(JourneyPart::Vehicle(leg)) => leg.wheelchair.alighting,
+            _ => true,
+        };
+        let boarding = match path.get(i + 1) {
+            Some(JourneyPart::Vehicle(leg)) => leg.wheelchair.boarding,
+            _ => true,
+        };
+        if let JourneyPart::Transfer(transfer) = &mut path[i] {
+            transfer.wheelchair_accessible = alighting && boarding;
+        }
+    }
+
+    path
+}
+
+/// Plan a journey that visits each of the `via` stops in order,
+/// staying at least `ViaStop.min_stay` at each of them.
+/// This runs a separate query for each part of the journey.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_raptor_via<'a>(
+    departure_stop: u32,
+    arrival_stop: u32,
+    via: &[ViaStop],
+    departure_time: impl Into<u32>,
+    timetable: &'a HashMap<u32, RRoute>,
+    transfer_times: &'a HashMap<u32, u32>,
+    options: &QueryOptions,
+    db: &impl Executor,
+) -> anyhow::Result<Option<Vec<JourneyPart>>> {
+    let mut path = Vec::new();
+    let mut from = departure_stop;
+    let mut time = departure_time.into();
+    // The via stop we arrived at, with the last trip leg before it and the time we stay there
+    let mut arrived_via: Option<(Location, TripLeg, u32)> = None;
+
+    for stop in via.iter().map(Some).chain([None]) {
+        let to = stop.map(|s| s.stop_id).unwrap_or(arrival_stop);
+        let Some(part) = run_raptor(from, to, time, timetable, transfer_times, options, db).await? else {
+            return Ok(None);
+        };
+        if let Some((via_stop, last_leg, stay)) = arrived_via.take() {
+            path.push(JourneyPart::Station(via_stop));
+            // Changing trains at the via stop is a transfer like any other
+            if let Some(next_leg) = part.iter().find_map(JourneyPart::vehicle) {
+                path.push(JourneyPart::Transfer(TransferLeg {
+                    from_stop: last_leg.to_stop.stop_id,
+                    to_stop: next_leg.from_stop.stop_id,
+                    duration: stay,
+                    wheelchair_accessible: last_leg.wheelchair.alighting && next_leg.wheelchair.boarding,
+                    risk: RiskLevel::Unknown,
+                }));
+            }
+        }
+        let last_leg = part.iter().rev().find_map(JourneyPart::vehicle).cloned();
+        path.extend(part);
+        if let (Some(stop), Some(last_leg)) = (stop, last_leg) {
+            // Continue from the via stop after the minimum stay, which is at least a transfer
+            let station_time = transfer_times.get(&last_leg.to_stop.stop_id)
+                .map(|t| t * 60).unwrap_or(0);
+            let stay = stop.min_stay.max(options.transfer_duration(station_time, None));
+            time = last_leg.arrival + stay;
+            from = stop.stop_id;
+            arrived_via = Some((Location { stop_id: stop.stop_id, parent_id: stop.stop_id }, last_leg, stay));
+        } else if let Some(stop) = stop {
+            // Already at the via stop, e.g. if it is the departure stop
+            time += stop.min_stay;
+            from = stop.stop_id;
+        }
+    }
+
     Ok(Some(path))
 }
 
@@ -0,0 +1,146 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use rbatis::executor::Executor;
+
+use crate::algorithms::raptor::{run_raptor, QueryOptions, RRoute};
+use crate::types::Journey;
+
+/// Options for finding alternative journeys
+#[derive(Debug, Clone)]
+pub struct AlternativesOptions {
+    /// Number of journeys to return
+    pub count: usize,
+    /// Only search for journeys departing within this time (in seconds) after the departure time
+    pub window: u32,
+    /// Journeys that share more than this fraction of their in-vehicle time
+    /// with a better journey are considered redundant
+    pub max_similarity: f64,
+}
+
+impl Default for AlternativesOptions {
+    fn default() -> Self {
+        Self {
+            count: 5,
+            window: 2 * 60 * 60,
+            max_similarity: 0.7,
+        }
+    }
+}
+
+/// Find multiple, diverse journeys between two stops.
+/// Candidates are found by querying again after the departure of each found journey,
+/// and by excluding each route of the best journey to find other corridors.
+/// The result is ranked by arrival time, number of transfers and departure time,
+/// without dominated or redundant journeys.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_raptor_alternatives(
+    departure_stop: u32,
+    arrival_stop: u32,
+    departure_time: impl Into<u32>,
+    timetable: &HashMap<u32, RRoute>,
+    transfer_times: &HashMap<u32, u32>,
+    options: &QueryOptions,
+    alternatives: &AlternativesOptions,
+    db: &impl Executor,
+) -> anyhow::Result<Vec<Journey>> {
+    let departure_time = departure_time.into();
+    let latest_departure = departure_time + alternatives.window;
+    let mut candidates: Vec<Journey> = Vec::new();
+
+    // Later departures
+    let mut time = departure_time;
+    while candidates.len() < alternatives.count * 2 {
+        let Some(parts) = run_raptor(
+            departure_stop, arrival_stop, time, timetable, transfer_times, options, db,
+        ).await? else { break };
+        let journey = Journey::from(parts);
+        let Some(departure) = journey.departure() else { break };
+        if departure > latest_departure { break; }
+        candidates.push(journey);
+        time = departure + 1;
+    }
+
+    // Other corridors, by not allowing one of the routes of the best journey
+    let best = candidates.iter()
+        .min_by_key(|j| (j.arrival(), j.transfers()))
+        .cloned();
+    if let Some(best) = best {
+        for leg in best.legs() {
+            let mut corridor_options = options.clone();
+            corridor_options.excluded_routes.insert(leg.route_id);
+            if let Some(parts) = run_raptor(
+                departure_stop, arrival_stop, departure_time, timetable, transfer_times,
+                &corridor_options, db,
+            ).await? {
+                candidates.push(Journey::from(parts));
+            }
+        }
+    }
+
+    Ok(rank_journeys(candidates, alternatives))
+}
+
+/// Find the journeys that arrive at or before `arrival_time` and depart as late as possible.
+/// Searches forward from `alternatives.window` before the arrival time, and returns
+/// at most `alternatives.count` journeys ordered by departure time.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_raptor_arrive_by(
+    departure_stop: u32,
+    arrival_stop: u32,
+    arrival_time: impl Into<u32>,
+    timetable: &HashMap<u32, RRoute>,
+    transfer_times: &HashMap<u32, u32>,
+    options: &QueryOptions,
+    alternatives: &AlternativesOptions,
+    db: &impl Executor,
+) -> anyhow::Result<Vec<Journey>> {
+    let arrival_time = arrival_time.into();
+    let mut journeys: Vec<Journey> = Vec::new();
+    let mut time = arrival_time.saturating_sub(alternatives.window);
+    loop {
+        let Some(parts) = run_raptor(
+            departure_stop, arrival_stop, time, timetable, transfer_times, options, db,
+        ).await? else { break };
+        let journey = Journey::from(parts);
+        let (Some(departure), Some(arrival)) = (journey.departure(), journey.arrival()) else { break };
+        // Later departures cannot arrive earlier
+        if arrival > arrival_time { break; }
+        journeys.push(journey);
+        time = departure + 1;
+    }
+
+    // Latest departures first, without journeys where a later one arrives just as early
+    let mut result: Vec<Journey> = Vec::new();
+    for journey in journeys.into_iter().rev() {
+        if result.len() >= alternatives.count {
+            break;
+        }
+        if !result.iter().any(|later| later.dominates(&journey)) {
+            result.push(journey);
+        }
+    }
+    result.reverse();
+    Ok(result)
+}
+
+/// Rank journeys and remove the ones that are dominated by or too similar to a better journey
+pub fn rank_journeys(mut journeys: Vec<Journey>, alternatives: &AlternativesOptions) -> Vec<Journey> {
+    journeys.sort_by_key(|j| (j.arrival(), j.transfers(), Reverse(j.departure())));
+
+    let mut result: Vec<Journey> = Vec::new();
+    for journey in journeys {
+        if result.len() >= alternatives.count {
+            break;
+        }
+        let redundant = result.iter().any(|better| {
+            better.dominates(&journey)
+                || better.similarity(&journey) > alternatives.max_similarity
+        });
+        if !redundant {
+            result.push(journey);
+        }
+    }
+
+    result
+}
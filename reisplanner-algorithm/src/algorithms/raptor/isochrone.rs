@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use geo::{BooleanOps, Coord, LineString, MultiPolygon, Polygon};
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use rbatis::RBatis;
+
+use crate::algorithms::raptor::{earliest_arrivals, Mode, QueryOptions, RRoute};
+use crate::database::queries::get_stop_locations;
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A stop that can be reached within the time budget of an isochrone query
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsochroneStop {
+    pub stop_id: u32,
+    pub stop_name: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// Earliest arrival in seconds since midnight
+    pub arrival: u32,
+    /// Travel time from the origin in seconds
+    pub travel_time: u32,
+}
+
+/// How to build polygons around the reachable stops.
+/// The radius around each stop is the distance that can be walked in the
+/// remaining time budget, limited to `max_radius`.
+#[derive(Debug, Clone)]
+pub struct BufferOptions {
+    /// Walking speed in meters per second
+    pub walking_speed: f64,
+    /// Maximum radius in meters
+    pub max_radius: f64,
+    /// Amount of line segments used to approximate a circle
+    pub segments: usize,
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            walking_speed: 1.25,
+            max_radius: 1000.0,
+            segments: 32,
+        }
+    }
+}
+
+/// Get the earliest arrival at all stops that can be reached from `departure_stop`
+/// within `budget` seconds.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_isochrone(
+    departure_stop: u32,
+    departure_time: impl Into<u32>,
+    budget: u32,
+    timetable: &HashMap<u32, RRoute>,
+    transfer_times: &HashMap<u32, u32>,
+    options: &QueryOptions,
+    db: &RBatis,
+) -> anyhow::Result<Vec<IsochroneStop>> {
+    let departure_time = departure_time.into();
+    let tau_star = earliest_arrivals(
+        departure_stop, None, departure_time, departure_time.saturating_add(budget),
+        timetable, transfer_times, options, db,
+    ).await?;
+    let locations = get_stop_locations(db).await?;
+
+    let mut result: Vec<_> = tau_star.values()
+        // Transfer labels are stored per platform, the stations are in the trip labels
+        .filter(|arrival| arrival.mode != Mode::Transfer)
+        .filter_map(|arrival| {
+            let stop_id = arrival.stop.parent_id;
+            let location = locations.get(&stop_id)?;
+            Some(IsochroneStop {
+                stop_id,
+                stop_name: location.stop_name.clone(),
+                lat: location.stop_lat,
+                lon: location.stop_lon,
+                arrival: arrival.time,
+                travel_time: arrival.time - departure_time,
+            })
+        })
+        .collect();
+    result.sort_by_key(|stop| (stop.travel_time, stop.stop_id));
+
+    Ok(result)
+}
+
+/// Create a GeoJSON feature collection with a point for each reachable stop.
+/// If `buffer` is given, a feature with the polygons around the stops is added.
+pub fn isochrone_to_geojson(
+    stops: &[IsochroneStop],
+    budget: u32,
+    buffer: Option<&BufferOptions>,
+) -> FeatureCollection {
+    let mut features: Vec<Feature> = stops.iter()
+        .map(|stop| {
+            let mut properties = JsonObject::new();
+            properties.insert("stop_id".to_string(), stop.stop_id.into());
+            properties.insert("stop_name".to_string(), stop.stop_name.clone().into());
+            properties.insert("arrival".to_string(), stop.arrival.into());
+            properties.insert("travel_time".to_string(), stop.travel_time.into());
+            Feature {
+                geometry: Some(Geometry::new(Value::Point(vec![stop.lon, stop.lat]))),
+                properties: Some(properties),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    if let Some(buffer) = buffer {
+        let polygon = buffer_stops(stops, budget, buffer);
+        let mut properties = JsonObject::new();
+        properties.insert("budget".to_string(), budget.into());
+        features.push(Feature {
+            geometry: Some(Geometry::from(&polygon)),
+            properties: Some(properties),
+            ..Default::default()
+        });
+    }
+
+    FeatureCollection {
+        features,
+        bbox: None,
+        foreign_members: None,
+    }
+}
+
+/// Union of the circles around the stops, with a radius based on the remaining budget
+pub fn buffer_stops(stops: &[IsochroneStop], budget: u32, buffer: &BufferOptions) -> MultiPolygon {
+    let mut polygons: Vec<MultiPolygon> = stops.iter()
+        .filter_map(|stop| {
+            let remaining = budget.saturating_sub(stop.travel_time) as f64;
+            let radius = (remaining * buffer.walking_speed).min(buffer.max_radius);
+            (radius > 0.0).then(|| MultiPolygon(vec![circle(stop.lat, stop.lon, radius, buffer.segments)]))
+        })
+        .collect();
+
+    // Merge pairwise, which keeps the polygons that are merged small
+    while polygons.len() > 1 {
+        polygons = polygons.chunks(2)
+            .map(|pair| match pair {
+                [a, b] => a.union(b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    polygons.pop().unwrap_or(MultiPolygon(Vec::new()))
+}
+
+/// Approximate a circle with a radius in meters around a coordinate
+fn circle(lat: f64, lon: f64, radius: f64, segments: usize) -> Polygon {
+    let d_lat = radius / METERS_PER_DEGREE;
+    let d_lon = radius / (METERS_PER_DEGREE * lat.to_radians().cos());
+    let coords: Vec<Coord> = (0..=segments)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / segments as f64;
+            Coord { x: lon + d_lon * angle.cos(), y: lat + d_lat * angle.sin() }
+        })
+        .collect();
+    Polygon::new(LineString(coords), Vec::new())
+}
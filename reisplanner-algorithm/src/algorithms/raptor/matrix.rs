@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use rbatis::RBatis;
+use serde::Serialize;
+use tokio::runtime::Handle;
+use tokio::task::JoinSet;
+
+use crate::algorithms::raptor::{earliest_arrivals, QueryOptions, RRoute};
+
+/// Departure window of a travel-time matrix query
+#[derive(Debug, Clone)]
+pub struct MatrixOptions {
+    /// First departure time in seconds since midnight
+    pub window_start: u32,
+    /// Last departure time in seconds since midnight
+    pub window_end: u32,
+    /// Time between the sampled departure times in seconds
+    pub step: u32,
+}
+
+impl Default for MatrixOptions {
+    fn default() -> Self {
+        Self {
+            window_start: 8 * 60 * 60,
+            window_end: 9 * 60 * 60,
+            step: 5 * 60,
+        }
+    }
+}
+
+impl MatrixOptions {
+    pub fn departure_times(&self) -> impl Iterator<Item = u32> {
+        (self.window_start..=self.window_end).step_by(self.step.max(1) as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatrixEntry {
+    pub from_stop: u32,
+    pub to_stop: u32,
+    /// Median travel time in seconds over the departure window, including waiting time
+    /// at the origin. None if the destination is unreachable for at least half of the departures.
+    pub median_travel_time: Option<u32>,
+    /// Amount of departure times from which the destination is reachable
+    pub reachable: usize,
+    /// Amount of sampled departure times
+    pub samples: usize,
+}
+
+/// Compute the travel times between all `origins` and `destinations` (parent station ids).
+/// A one-to-all query is done for every origin and departure time in the window,
+/// the origins are processed in parallel on the blocking thread pool.
+pub async fn run_travel_time_matrix(
+    origins: &[u32],
+    destinations: &[u32],
+    timetable: Arc<HashMap<u32, RRoute>>,
+    transfer_times: Arc<HashMap<u32, u32>>,
+    options: Arc<QueryOptions>,
+    matrix: &MatrixOptions,
+    db: &RBatis,
+) -> anyhow::Result<Vec<MatrixEntry>> {
+    let destinations = Arc::new(destinations.to_vec());
+    let mut tasks = JoinSet::new();
+
+    for (index, &origin) in origins.iter().enumerate() {
+        let timetable = timetable.clone();
+        let transfer_times = transfer_times.clone();
+        let options = options.clone();
+        let destinations = destinations.clone();
+        let matrix = matrix.clone();
+        let db = db.clone();
+        let runtime = Handle::current();
+
+        // The queries are CPU-bound, running them on the async workers would stall other tasks
+        tasks.spawn_blocking(move || {
+            let mut samples = vec![Vec::new(); destinations.len()];
+            for departure_time in matrix.departure_times() {
+                let tau_star = runtime.block_on(earliest_arrivals(
+                    origin, None, departure_time, u32::MAX,
+                    &timetable, &transfer_times, &options, &db,
+                ))?;
+                for (i, destination) in destinations.iter().enumerate() {
+                    samples[i].push(tau_star.get(destination).map(|a| a.time - departure_time));
+                }
+            }
+
+            let entries: Vec<_> = destinations.iter().zip(samples)
+                .map(|(&to_stop, samples)| MatrixEntry {
+                    from_stop: origin,
+                    to_stop,
+                    median_travel_time: median_travel_time(&samples),
+                    reachable: samples.iter().filter(|s| s.is_some()).count(),
+                    samples: samples.len(),
+                })
+                .collect();
+            anyhow::Ok((index, entries))
+        });
+    }
+
+    let mut results = Vec::with_capacity(origins.len());
+    while let Some(result) = tasks.join_next().await {
+        results.push(result??);
+    }
+    // Keep the order of the origins
+    results.sort_by_key(|(index, _)| *index);
+
+    Ok(results.into_iter().flat_map(|(_, entries)| entries).collect())
+}
+
+/// Median of the travel times, where unreachable samples count as infinitely long
+pub fn median_travel_time(samples: &[Option<u32>]) -> Option<u32> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<u32> = samples.iter().map(|s| s.unwrap_or(u32::MAX)).collect();
+    sorted.sort();
+    let median = if sorted.len() % 2 == 1 {
+        sorted[sorted.len() / 2]
+    } else {
+        let (a, b) = (sorted[sorted.len() / 2 - 1], sorted[sorted.len() / 2]);
+        if b == u32::MAX { u32::MAX } else { (a + b) / 2 }
+    };
+    (median != u32::MAX).then_some(median)
+}
+
+pub fn write_matrix_csv<W: Write>(
+    entries: &[MatrixEntry],
+    names: &HashMap<u32, String>,
+    writer: W,
+) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct Row<'a> {
+        from_stop: u32,
+        from_name: Option<&'a str>,
+        to_stop: u32,
+        to_name: Option<&'a str>,
+        median_travel_time: Option<u32>,
+        reachable: usize,
+        samples: usize,
+    }
+
+    let mut writer = csv::Writer::from_writer(writer);
+    for entry in entries {
+        writer.serialize(Row {
+            from_stop: entry.from_stop,
+            from_name: names.get(&entry.from_stop).map(String::as_str),
+            to_stop: entry.to_stop,
+            to_name: names.get(&entry.to_stop).map(String::as_str),
+            median_travel_time: entry.median_travel_time,
+            reachable: entry.reachable,
+            samples: entry.samples,
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate};
+
+use reisplanner_gtfs::gtfs::types::{AllowedType, RouteType};
+use reisplanner_gtfs::utils::TimeTuple;
+
+use crate::algorithms::raptor::{Connection, Location, RRoute, TripAttributes};
+
+/// Extra time (in seconds) added to each transfer for wheelchair users
+pub const WHEELCHAIR_EXTRA_TRANSFER_TIME: u32 = 5 * 60;
+
+/// Period of the day, in seconds since midnight
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TimeWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl TimeWindow {
+    pub fn new(start: impl Into<u32>, end: impl Into<u32>) -> Self {
+        Self { start: start.into(), end: end.into() }
+    }
+
+    /// Check if the period between `from` and `to` overlaps with this window
+    pub fn overlaps(&self, from: u32, to: u32) -> bool {
+        from < self.end && to > self.start
+    }
+}
+
+/// Options for travelling with a bicycle
+#[derive(Debug, Clone, Default)]
+pub struct BicycleOptions {
+    /// Also use trips that have no information about bicycles
+    pub include_no_information: bool,
+    /// Periods in which bicycles are not allowed on board, e.g. rush hours,
+    /// in GTFS time (so may exceed 24 hours) on the service date of the query
+    pub restricted_windows: Vec<TimeWindow>,
+}
+
+impl BicycleOptions {
+    /// Only trips that allow bicycles, outside the NS rush hours on weekdays
+    /// (06:30-09:00 and 16:00-18:30). There are no rush hours in July and August.
+    /// Times after midnight belong to the next day, which may be in the weekend or in July.
+    pub fn ns_rush_hours(date: NaiveDate) -> Self {
+        let rush_hours = [
+            (TimeTuple(6, 30, 0), TimeTuple(9, 0, 0)),
+            (TimeTuple(16, 0, 0), TimeTuple(18, 30, 0)),
+        ];
+        let has_rush_hours = |d: NaiveDate| d.weekday().number_from_monday() <= 5 && !matches!(d.month(), 7 | 8);
+        let mut restricted_windows = Vec::new();
+        for (days, date) in [(0, Some(date)), (1, date.succ_opt())] {
+            if date.is_some_and(has_rush_hours) {
+                restricted_windows.extend(rush_hours.iter().map(|&(start, end)| TimeWindow::new(
+                    u32::from(start) + days * 24 * 3600,
+                    u32::from(end) + days * 24 * 3600,
+                )));
+            }
+        }
+        Self { include_no_information: false, restricted_windows }
+    }
+}
+
+/// A stop that a journey should visit, see `run_raptor_via`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ViaStop {
+    /// Parent stop_id but without the `stopearea:` prefix
+    pub stop_id: u32,
+    /// Minimum time (in seconds) to stay at this stop
+    pub min_stay: u32,
+}
+
+/// Options that restrict which parts of the timetable a query may use.
+/// The default allows everything.
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Only use routes of these types (e.g. train only), `None` allows all types
+    pub allowed_route_types: Option<HashSet<RouteType>>,
+    /// Never use routes of these types (e.g. no buses)
+    pub excluded_route_types: HashSet<RouteType>,
+    /// Only use routes of these operators (`Route.agency_id`), `None` allows all operators
+    pub allowed_agencies: Option<HashSet<String>>,
+    /// Never use routes of these operators
+    pub excluded_agencies: HashSet<String>,
+    /// Only board wheelchair accessible trips at wheelchair accessible stops,
+    /// and use longer transfer times
+    pub wheelchair: bool,
+    /// Only use trips where a bicycle can be taken along, `None` when travelling without bicycle
+    pub bicycle: Option<BicycleOptions>,
+    /// Stops (parent stop_ids) that the journey may not board, alight or pass through
+    pub avoided_stops: HashSet<u32>,
+    /// Extra time (in seconds) added to every transfer, e.g. for slow walkers or heavy luggage
+    pub transfer_buffer: u32,
+    /// Minimum transfer time (in seconds) when transferring from the first to the second route type,
+    /// e.g. from train to bus
+    pub min_transfer_times: HashMap<(RouteType, RouteType), u32>,
+    /// Maximum total time (in seconds) spent transferring in a journey
+    pub max_transfer_time: Option<u32>,
+    /// Routes (keys in the timetable) that may not be used,
+    /// e.g. to find a journey through a different corridor
+    pub excluded_routes: HashSet<u32>,
+    /// Only use trips of these services, usually those that run on the date of the query
+    /// (see `get_active_services`). `None` uses all trips, regardless of their dates.
+    /// Trips of the previous service date that run after midnight are not included.
+    pub services: Option<HashSet<u32>>,
+}
+
+impl QueryOptions {
+    /// Options for travellers that can only use trains, e.g. with a rail pass
+    pub fn train_only() -> Self {
+        Self {
+            allowed_route_types: Some(HashSet::from([RouteType::Train])),
+            ..Default::default()
+        }
+    }
+
+    pub fn allows_route_type(&self, route_type: &RouteType) -> bool {
+        !self.excluded_route_types.contains(route_type)
+            && self.allowed_route_types.as_ref()
+            .is_none_or(|allowed| allowed.contains(route_type))
+    }
+
+    pub fn allows_agency(&self, agency_id: &str) -> bool {
+        !self.excluded_agencies.contains(agency_id)
+            && self.allowed_agencies.as_ref()
+            .is_none_or(|allowed| allowed.contains(agency_id))
+    }
+
+    /// Check if the route may be scanned for this query
+    pub fn allows_route(&self, route: &RRoute) -> bool {
+        self.allows_route_type(&route.route_type) && self.allows_agency(&route.agency_id)
+    }
+
+    /// Check if a trip may be boarded for this query
+    pub fn allows_trip(&self, trip: &TripAttributes) -> bool {
+        if self.services.as_ref().is_some_and(|services| !services.contains(&trip.service_id)) {
+            return false;
+        }
+        if self.wheelchair && trip.wheelchair_accessible != AllowedType::Allowed {
+            return false;
+        }
+        if let Some(bicycle) = &self.bicycle {
+            return match trip.bikes_allowed {
+                AllowedType::Allowed => true,
+                AllowedType::NoInformation => bicycle.include_no_information,
+                AllowedType::NotAllowed => false,
+            };
+        }
+        true
+    }
+
+    /// Check if we may travel over this connection for this query
+    pub fn allows_connection(&self, connection: &Connection) -> bool {
+        self.bicycle.as_ref().is_none_or(|bicycle| {
+            !bicycle.restricted_windows.iter()
+                .any(|w| w.overlaps(connection.departure, connection.arrival))
+        })
+    }
+
+    /// Check if the journey should not visit this stop
+    pub fn avoids(&self, stop: &Location) -> bool {
+        self.avoided_stops.contains(&stop.parent_id)
+    }
+
+    /// Check if we may board or alight at this stop of the route
+    pub fn allows_stop(&self, route: &RRoute, stop: &Location) -> bool {
+        !self.avoids(stop) && (!self.wheelchair || route.is_wheelchair_accessible(stop.stop_id))
+    }
+
+    /// Get the transfer duration (in seconds) to use for this query,
+    /// given the transfer time of the station (in seconds) and the route types
+    /// we transfer between, if known.
+    /// All transfers in a query should be created using this function.
+    pub fn transfer_duration(&self, station_time: u32, modes: Option<(RouteType, RouteType)>) -> u32 {
+        let mut duration = station_time;
+        if let Some(&min_time) = modes.and_then(|m| self.min_transfer_times.get(&m)) {
+            duration = duration.max(min_time);
+        }
+        duration += self.transfer_buffer;
+        if self.wheelchair {
+            duration += WHEELCHAIR_EXTRA_TRANSFER_TIME;
+        }
+        duration
+    }
+
+    /// Check if the total transfer time (in seconds) of a journey is allowed
+    pub fn allows_transfer_time(&self, total: u32) -> bool {
+        self.max_transfer_time.is_none_or(|max| total <= max)
+    }
+}
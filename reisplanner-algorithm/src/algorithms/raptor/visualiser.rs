@@ -16,28 +16,35 @@ pub async fn visualise_earliest_arrivals(
     destination: u32,
     db: &impl Executor,
 ) -> anyhow::Result<()> {
+    // Look up the names first, the writer cannot be held across await points
+    let mut edges = Vec::with_capacity(tau.len());
+    for (_, arrival) in tau.iter() {
+        let name = get_stop_readable(&arrival.stop.parent_id, db).await?;
+        let from_station = match arrival.departure_stop {
+            Some(departure_stop) => Some(get_stop_readable(&departure_stop.parent_id, db).await?),
+            None => None,
+        };
+        edges.push((from_station, name, seconds_to_hms(arrival.time)));
+    }
+    let destination_name = get_stop_readable(&destination, db).await?;
+
     let mut output_bytes = Vec::new();
     {
         let mut writer = DotWriter::from(&mut output_bytes);
 
         let mut digraph = writer.digraph();
 
-        for (_, arrival) in tau.iter() {
-            let name = get_stop_readable(&arrival.stop.parent_id, db).await?;
-            if arrival.departure_stop.is_some() {
-                let from_station = get_stop_readable(&arrival.departure_stop.unwrap().parent_id, db).await?;
+        for (from_station, name, arrival) in edges {
+            if let Some(from_station) = from_station {
                 // let route = c2.route_information(db).await?;
-                let arrival = seconds_to_hms(arrival.time);
-
                 digraph.edge(format!("\"{from_station}\""), format!("\"{name}\""))
-                    .attributes().set_label(&arrival.to_string());
+                    .attributes().set_label(&arrival);
             } else {
                 digraph.node_named(format!("\"{name}\""))
                     .set_color(Color::Red).set_style(Style::Filled);
             }
         }
         
-        let destination_name = get_stop_readable(&destination, db).await?;
         digraph.node_named(format!("\"{destination_name}\""))
             .set_color(Color::PaleGreen).set_style(Style::Filled);
     }
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{read_to_string, File};
+use std::io::{stdout, Write};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use chrono::{NaiveTime, Timelike};
+use tracing_subscriber::EnvFilter;
+
+use reisplanner_algorithm::algorithms::raptor;
+use reisplanner_algorithm::algorithms::raptor::matrix::{run_travel_time_matrix, write_matrix_csv, MatrixOptions};
+use reisplanner_algorithm::algorithms::raptor::QueryOptions;
+use reisplanner_algorithm::database::new_db_connection;
+use reisplanner_algorithm::database::queries::get_stop_locations;
+
+const USAGE: &str = "Usage: travel_time_matrix --from IDS|FILE --to IDS|FILE [--start HH:MM] [--end HH:MM] \
+[--step MINUTES] [--train-only] [--output FILE]
+IDS is a comma separated list of station ids, FILE contains one station id per line";
+
+/// Compute the median travel times between sets of stations and write them as CSV
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let log_level = EnvFilter::try_from_default_env()
+        .unwrap_or(EnvFilter::new("error,reisplanner=debug"));
+    tracing_subscriber::fmt().with_env_filter(log_level).with_writer(std::io::stderr).init();
+
+    let mut origins = Vec::new();
+    let mut destinations = Vec::new();
+    let mut matrix = MatrixOptions::default();
+    let mut options = QueryOptions::default();
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(anyhow!("Missing value for {arg}\n{USAGE}"));
+        match arg.as_str() {
+            "--from" => origins = parse_stops(&value()?)?,
+            "--to" => destinations = parse_stops(&value()?)?,
+            "--start" => matrix.window_start = parse_time(&value()?)?,
+            "--end" => matrix.window_end = parse_time(&value()?)?,
+            "--step" => matrix.step = value()?.parse::<u32>()? * 60,
+            "--train-only" => options = QueryOptions::train_only(),
+            "--output" => output = Some(value()?),
+            _ => return Err(anyhow!("Unknown argument {arg}\n{USAGE}")),
+        }
+    }
+    if origins.is_empty() || destinations.is_empty() {
+        return Err(anyhow!("No origins or destinations given\n{USAGE}"));
+    }
+
+    let db = new_db_connection()?;
+    let timetable = Arc::new(raptor::get_timetable(&db, true).await?);
+    let transfer_times = Arc::new(raptor::generate_transfer_times(&db).await?);
+
+    let entries = run_travel_time_matrix(
+        &origins, &destinations, timetable, transfer_times, Arc::new(options), &matrix, &db,
+    ).await?;
+
+    let names: HashMap<u32, String> = get_stop_locations(&db).await?.into_iter()
+        .map(|(id, stop)| (id, stop.stop_name))
+        .collect();
+    let writer: Box<dyn Write> = match output {
+        None => Box::new(stdout()),
+        Some(path) => Box::new(File::create(path)?),
+    };
+    write_matrix_csv(&entries, &names, writer)
+}
+
+/// Parse a comma separated list of stop ids, or read them from a file
+fn parse_stops(value: &str) -> anyhow::Result<Vec<u32>> {
+    let content = match value.chars().all(|c| c.is_ascii_digit() || c == ',') {
+        true => value.replace(',', "\n"),
+        false => read_to_string(value).with_context(|| format!("Could not read {value}"))?,
+    };
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse().with_context(|| format!("Invalid stop id {line}")))
+        .collect()
+}
+
+fn parse_time(value: &str) -> anyhow::Result<u32> {
+    let time = NaiveTime::parse_from_str(value, "%H:%M")
+        .with_context(|| format!("Invalid time {value}"))?;
+    Ok(time.num_seconds_from_midnight())
+}
@@ -0,0 +1,184 @@
+use chrono::{Days, NaiveDate};
+use rbatis::RBatis;
+use serde::Serialize;
+
+use reisplanner_gtfs::gtfs::types::RouteType;
+
+use crate::database::queries::{get_stop_arrivals, get_stop_departures, StopVisit};
+use crate::export::{feed_seconds, now};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// A departure of a trip from a stop, as shown on a departure board
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Departure {
+    pub trip_id: u32,
+    pub stop_id: u32,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    pub trip_short_name: Option<String>,
+    pub headsign: String,
+    pub platform_code: Option<String>,
+    /// Scheduled departure in seconds since midnight of the requested date
+    pub scheduled_departure: u32,
+    /// Realtime departure delay in seconds
+    pub departure_delay: Option<i32>,
+    pub cancelled: bool,
+}
+
+impl Departure {
+    /// The departure time including the realtime delay
+    pub fn expected_departure(&self) -> u32 {
+        expected_time(self.scheduled_departure, self.departure_delay)
+    }
+
+    fn from_visit(visit: StopVisit, day_offset: u32) -> Self {
+        Self {
+            trip_id: visit.trip_id,
+            stop_id: visit.stop_id,
+            route_id: visit.route_id,
+            route_short_name: visit.route_short_name,
+            route_type: visit.route_type,
+            agency_id: visit.agency_id,
+            trip_short_name: visit.trip_short_name,
+            headsign: visit.stop_headsign.filter(|h| !h.is_empty()).unwrap_or(visit.trip_headsign),
+            platform_code: visit.platform_code,
+            scheduled_departure: visit.departure_time - day_offset,
+            departure_delay: visit.departure_delay,
+            cancelled: visit.cancelled,
+        }
+    }
+}
+
+/// An arrival of a trip at a stop, as shown on an arrival board
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Arrival {
+    pub trip_id: u32,
+    pub stop_id: u32,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    pub trip_short_name: Option<String>,
+    /// Name of the first stop of the trip
+    pub origin: String,
+    pub platform_code: Option<String>,
+    /// Scheduled arrival in seconds since midnight of the requested date
+    pub scheduled_arrival: u32,
+    /// Realtime arrival delay in seconds
+    pub arrival_delay: Option<i32>,
+    /// Arrival time including the realtime delay
+    pub predicted_arrival: u32,
+    pub cancelled: bool,
+}
+
+impl Arrival {
+    fn from_visit(visit: StopVisit, day_offset: u32) -> Self {
+        let scheduled_arrival = visit.arrival_time - day_offset;
+        Self {
+            trip_id: visit.trip_id,
+            stop_id: visit.stop_id,
+            route_id: visit.route_id,
+            route_short_name: visit.route_short_name,
+            route_type: visit.route_type,
+            agency_id: visit.agency_id,
+            trip_short_name: visit.trip_short_name,
+            origin: visit.origin,
+            platform_code: visit.platform_code,
+            scheduled_arrival,
+            arrival_delay: visit.arrival_delay,
+            predicted_arrival: expected_time(scheduled_arrival, visit.arrival_delay),
+            cancelled: visit.cancelled,
+        }
+    }
+}
+
+pub(crate) fn expected_time(scheduled: u32, delay: Option<i32>) -> u32 {
+    (scheduled as i64 + delay.unwrap_or(0) as i64).max(0) as u32
+}
+
+/// Get the next `limit` departures from a stop or parent station at `date` after `time`
+/// (in seconds since midnight), ordered by scheduled departure time.
+/// Realtime delays and cancellations are only used if `date` is today in the time zone of the feed,
+/// each service date with its own delays.
+/// Trips of the previous service date that depart after midnight are included as well.
+pub async fn get_departures(
+    stop_id: u32,
+    date: NaiveDate,
+    time: impl Into<u32>,
+    limit: usize,
+    db: &RBatis,
+) -> anyhow::Result<Vec<Departure>> {
+    let time = time.into();
+    let now = now();
+    let realtime = date == now.date_naive();
+    let seconds = |date| feed_seconds(date, now).filter(|_| realtime);
+
+    let mut departures: Vec<Departure> = get_stop_departures(stop_id, date, time, seconds(date), limit, db)
+        .await?
+        .into_iter()
+        .map(|visit| Departure::from_visit(visit, 0))
+        .collect();
+    if let Some(previous) = date.checked_sub_days(Days::new(1)) {
+        let visits = get_stop_departures(
+            stop_id, previous, time + SECONDS_PER_DAY, seconds(previous), limit, db,
+        ).await?;
+        departures.extend(visits.into_iter()
+            .map(|visit| Departure::from_visit(visit, SECONDS_PER_DAY)));
+    }
+
+    departures.sort_by_key(|d| (d.scheduled_departure, d.trip_id));
+    departures.truncate(limit);
+    if !realtime {
+        for departure in &mut departures {
+            departure.departure_delay = None;
+            departure.cancelled = false;
+        }
+    }
+
+    Ok(departures)
+}
+
+/// Get the next `limit` arrivals at a stop or parent station at `date` after `time`
+/// (in seconds since midnight), ordered by scheduled arrival time.
+/// Realtime delays and cancellations are only used if `date` is today in the time zone of the feed,
+/// each service date with its own delays.
+pub async fn get_arrivals(
+    stop_id: u32,
+    date: NaiveDate,
+    time: impl Into<u32>,
+    limit: usize,
+    db: &RBatis,
+) -> anyhow::Result<Vec<Arrival>> {
+    let time = time.into();
+    let now = now();
+    let realtime = date == now.date_naive();
+    let seconds = |date| feed_seconds(date, now).filter(|_| realtime);
+
+    let mut arrivals: Vec<Arrival> = get_stop_arrivals(stop_id, date, time, seconds(date), limit, db)
+        .await?
+        .into_iter()
+        .map(|visit| Arrival::from_visit(visit, 0))
+        .collect();
+    if let Some(previous) = date.checked_sub_days(Days::new(1)) {
+        let visits = get_stop_arrivals(
+            stop_id, previous, time + SECONDS_PER_DAY, seconds(previous), limit, db,
+        ).await?;
+        arrivals.extend(visits.into_iter()
+            .map(|visit| Arrival::from_visit(visit, SECONDS_PER_DAY)));
+    }
+
+    arrivals.sort_by_key(|a| (a.scheduled_arrival, a.trip_id));
+    arrivals.truncate(limit);
+    if !realtime {
+        for arrival in &mut arrivals {
+            arrival.arrival_delay = None;
+            arrival.predicted_arrival = arrival.scheduled_arrival;
+            arrival.cancelled = false;
+        }
+    }
+
+    Ok(arrivals)
+}
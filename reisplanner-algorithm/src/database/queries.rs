@@ -1,11 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use anyhow::Context;
+use chrono::NaiveDate;
 use rbatis::RBatis;
 use serde::Deserialize;
 
-use reisplanner_gtfs::utils::TimeTuple;
+use reisplanner_gtfs::gtfs::types::{AllowedType, PickupType, RouteType, ShapePolyline, WheelchairBoarding};
+use reisplanner_gtfs::utils::{deserialize_bool, TimeTuple};
 
 #[derive(Deserialize)]
 struct StationParent {
@@ -36,6 +38,63 @@ pub async fn get_parent_station_map(db: &RBatis) -> anyhow::Result<HashMap<u32,
     Ok(map)
 }
 
+#[derive(Deserialize)]
+struct StopWheelchair {
+    stop_id: String,
+    parent_station: Option<String>,
+    wheelchair_boarding: Option<WheelchairBoarding>,
+}
+
+/// Get the ids of all stops where boarding with a wheelchair is possible.
+/// Stops without information inherit the value of their parent station.
+pub async fn get_wheelchair_stops(db: &RBatis) -> anyhow::Result<HashSet<u32>> {
+    let stops: Vec<StopWheelchair> = db
+        .query_decode("select stop_id, parent_station, wheelchair_boarding from stop", vec![])
+        .await?;
+
+    let mut boarding = HashMap::with_capacity(stops.len());
+    for stop in &stops {
+        boarding.insert(stop.stop_id.as_str(), stop.wheelchair_boarding.clone());
+    }
+
+    let mut result = HashSet::new();
+    for stop in &stops {
+        let mut value = stop.wheelchair_boarding.clone();
+        if matches!(value, None | Some(WheelchairBoarding::Empty)) {
+            if let Some(parent) = &stop.parent_station {
+                value = boarding.get(parent.as_str()).cloned().flatten();
+            }
+        }
+        if value == Some(WheelchairBoarding::SomePossible) {
+            result.insert(parse_stop_id(&stop.stop_id)?);
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopLocation {
+    pub stop_id: String,
+    pub stop_name: String,
+    pub stop_lat: f64,
+    pub stop_lon: f64,
+}
+
+/// Get the name and location of all stops and stop areas
+pub async fn get_stop_locations(db: &RBatis) -> anyhow::Result<HashMap<u32, StopLocation>> {
+    let stops: Vec<StopLocation> = db
+        .query_decode("select stop_id, stop_name, stop_lat, stop_lon from stop", vec![])
+        .await?;
+
+    let mut map = HashMap::with_capacity(stops.len());
+    for stop in stops {
+        map.insert(parse_stop_id(&stop.stop_id)?, stop);
+    }
+
+    Ok(map)
+}
+
 pub fn parse_stop_id(stop_id: &String) -> anyhow::Result<u32> {
     // stop_id is "stoparea:123456", so we parse to just 123456
     // we assume that no regular stop 123456 exists
@@ -98,6 +157,38 @@ pub async fn get_trip_route_map(db: &RBatis) -> anyhow::Result<HashMap<u32, u32>
 }
 
 
+#[derive(Deserialize)]
+pub struct TripRouteInfo {
+    pub trip_id: u32,
+    pub service_id: u32,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    pub wheelchair_accessible: Option<AllowedType>,
+    pub bikes_allowed: Option<AllowedType>,
+}
+
+
+/// Get the type and operator of the GTFS route that each trip belongs to,
+/// together with the service, the accessibility of the trip and if bicycles are allowed
+pub async fn get_trip_route_info_map(db: &RBatis) -> anyhow::Result<HashMap<u32, TripRouteInfo>> {
+    let trips: Vec<TripRouteInfo> = db
+        .query_decode(
+            "select t.trip_id, t.service_id, r.route_type, r.agency_id, t.wheelchair_accessible, t.bikes_allowed
+                from trip t
+                join route r on r.route_id = t.route_id",
+            vec![])
+        .await?;
+
+    let mut map = HashMap::with_capacity(trips.len());
+
+    for trip in trips {
+        map.insert(trip.trip_id, trip);
+    }
+
+    Ok(map)
+}
+
+
 #[derive(Deserialize)]
 struct TransferTime {
     stop_id: String,
@@ -121,4 +212,427 @@ pub async fn get_transfer_times(db: &RBatis) -> anyhow::Result<HashMap<u32, u32>
     }
 
     Ok(map)
-}
\ No newline at end of file
+}
+
+/// An observed arrival delay of a trip at a stop
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopDelay {
+    pub trip_id: u32,
+    pub route_id: u32,
+    pub stop_id: u32,
+    pub arrival_delay: i32,
+}
+
+/// Get the historical arrival delays (in seconds) of all trips at these stops,
+/// using the last observation of each trip and stop per service date
+pub async fn get_arrival_delays(stop_ids: &[u32], db: &RBatis) -> anyhow::Result<Vec<StopDelay>> {
+    if stop_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids = stop_ids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+    let delays: Vec<StopDelay> = db
+        .query_decode(
+            format!("select trip_id, route_id, stop_id, arrival_delay, max(observed_at) as observed_at
+                from delay_record
+                where stop_id in ({ids}) and arrival_delay is not null
+                group by service_date, trip_id, stop_sequence").as_str(),
+            vec![],
+        ).await?;
+
+    Ok(delays)
+}
+
+/// SQL expression for the seconds since midnight of a `TimeTuple` column,
+/// which is stored as `[hours, minutes, seconds]`
+pub fn time_seconds_sql(column: &str) -> String {
+    format!("(json_extract({column}, '$[0]') * 3600 + json_extract({column}, '$[1]') * 60 \
+        + json_extract({column}, '$[2]'))")
+}
+
+/// SQL expression for whether stop time `st` is cancelled on `date` (formatted as `%Y-%m-%d`),
+/// according to the last observation in the delay archive
+fn cancelled_sql(date: &str) -> String {
+    format!("coalesce((
+        select d.cancelled from delay_record d
+        where d.service_date = '{date}' and d.trip_id = st.trip_id and d.stop_sequence = st.stop_sequence
+        order by d.observed_at desc limit 1
+    ), 0)")
+}
+
+/// SQL expression for delay `column` (`arrival_delay` or `departure_delay`) of stop time `st`
+/// on `date` (formatted as `%Y-%m-%d`), according to the last observation in the delay archive.
+/// `stop_time` only holds the last delay of a trip, whichever date it ran on,
+/// so it is only used if the trip runs at `now` (seconds since the start of `date`).
+/// Null without `now`, if no realtime information is used.
+fn delay_sql(column: &str, date: &str, now: Option<u32>) -> String {
+    let Some(now) = now else { return "null".to_string() };
+    format!("coalesce((
+        select d.{column} from delay_record d
+        where d.service_date = '{date}' and d.trip_id = st.trip_id and d.stop_sequence = st.stop_sequence
+        and d.{column} is not null
+        order by d.observed_at desc limit 1
+    ), case when {now} between (
+        select min({}) from stop_time f where f.trip_id = st.trip_id
+    ) and (
+        select max({}) from stop_time l where l.trip_id = st.trip_id
+    ) then st.{column} end)", time_seconds_sql("f.departure_time"), time_seconds_sql("l.arrival_time"))
+}
+
+/// A trip stopping at a stop, with information about the trip and route
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopVisit {
+    pub trip_id: u32,
+    pub stop_id: u32,
+    pub stop_sequence: u32,
+    pub arrival_time: u32,
+    pub departure_time: u32,
+    pub arrival_delay: Option<i32>,
+    pub departure_delay: Option<i32>,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub cancelled: bool,
+    pub stop_headsign: Option<String>,
+    pub platform_code: Option<String>,
+    pub trip_headsign: String,
+    pub trip_short_name: Option<String>,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    /// Name of the first stop of the trip
+    pub origin: String,
+}
+
+/// Get the trips that depart from `stop_id` (a stop or a parent station) on `date`,
+/// with a departure time of at least `from_time` (including the delay, if `now` is set, see `delay_sql`).
+/// Stops where boarding is not possible are excluded.
+pub async fn get_stop_departures(
+    stop_id: u32, date: NaiveDate, from_time: u32, now: Option<u32>, limit: usize, db: &RBatis,
+) -> anyhow::Result<Vec<StopVisit>> {
+    let departure = time_seconds_sql("st.departure_time");
+    let delay = delay_sql("departure_delay", &date.format("%Y-%m-%d").to_string(), now);
+    get_stop_visits(
+        stop_id, date, now,
+        &format!("st.pickup_type != 1 and {departure} + coalesce({delay}, 0) >= {from_time}"),
+        &departure, limit, db,
+    ).await
+}
+
+/// Get the trips that arrive at `stop_id` (a stop or a parent station) on `date`,
+/// with an arrival time of at least `from_time` (including the delay, if `now` is set, see `delay_sql`).
+/// Stops where alighting is not possible (pickup only) are excluded.
+pub async fn get_stop_arrivals(
+    stop_id: u32, date: NaiveDate, from_time: u32, now: Option<u32>, limit: usize, db: &RBatis,
+) -> anyhow::Result<Vec<StopVisit>> {
+    let arrival = time_seconds_sql("st.arrival_time");
+    let delay = delay_sql("arrival_delay", &date.format("%Y-%m-%d").to_string(), now);
+    get_stop_visits(
+        stop_id, date, now,
+        &format!("st.drop_off_type != 1 and {arrival} + coalesce({delay}, 0) >= {from_time}"),
+        &arrival, limit, db,
+    ).await
+}
+
+async fn get_stop_visits(
+    stop_id: u32, date: NaiveDate, now: Option<u32>, filter: &str, order: &str, limit: usize, db: &RBatis,
+) -> anyhow::Result<Vec<StopVisit>> {
+    let date = date.format("%Y-%m-%d").to_string();
+    let visits: Vec<StopVisit> = db
+        .query_decode(
+            format!("select st.trip_id, st.stop_id, st.stop_sequence,
+                {} as arrival_time, {} as departure_time,
+                {} as arrival_delay, {} as departure_delay, {} as cancelled, st.stop_headsign, s.platform_code,
+                t.trip_headsign, t.trip_short_name, t.route_id,
+                r.route_short_name, r.route_type, r.agency_id,
+                (
+                    select o.stop_name from stop_time ost join stop o on o.stop_id = ost.stop_id
+                    where ost.trip_id = st.trip_id order by ost.stop_sequence limit 1
+                ) as origin
+                from stop_time st
+                join stop s on s.stop_id = st.stop_id
+                join trip t on t.trip_id = st.trip_id
+                join route r on r.route_id = t.route_id
+                where st.stop_id in (
+                    select stop_id from stop
+                    where stop_id = '{stop_id}' or parent_station = 'stoparea:{stop_id}'
+                )
+                and t.service_id in (
+                    select service_id from calendar_date where date = '{date}' and exception_type = 1
+                )
+                and {filter}
+                order by {order}
+                limit {limit}",
+                    time_seconds_sql("st.arrival_time"), time_seconds_sql("st.departure_time"),
+                    delay_sql("arrival_delay", &date, now), delay_sql("departure_delay", &date, now),
+                    cancelled_sql(&date),
+            ).as_str(),
+            vec![],
+        ).await?;
+
+    Ok(visits)
+}
+
+/// A stop of a trip, with information about the stop
+#[derive(Deserialize, Debug, Clone)]
+pub struct TripStopRow {
+    pub stop_sequence: u32,
+    pub stop_id: u32,
+    pub stop_name: String,
+    pub platform_code: Option<String>,
+    pub arrival_time: u32,
+    pub departure_time: u32,
+    pub arrival_delay: Option<i32>,
+    pub departure_delay: Option<i32>,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub cancelled: bool,
+    pub pickup_type: PickupType,
+    pub drop_off_type: PickupType,
+}
+
+/// Get all stops of a trip, ordered by stop sequence, with the cancellations of service date `date`
+/// and its delays if `now` is set (see `delay_sql`)
+pub async fn get_trip_stops(
+    trip_id: u32, date: NaiveDate, now: Option<u32>, db: &RBatis,
+) -> anyhow::Result<Vec<TripStopRow>> {
+    let date = date.format("%Y-%m-%d").to_string();
+    let stops: Vec<TripStopRow> = db
+        .query_decode(
+            format!("select st.stop_sequence, st.stop_id, s.stop_name, s.platform_code,
+                {} as arrival_time, {} as departure_time,
+                {} as arrival_delay, {} as departure_delay, {} as cancelled, st.pickup_type, st.drop_off_type
+                from stop_time st
+                join stop s on s.stop_id = st.stop_id
+                where st.trip_id = {trip_id}
+                order by st.stop_sequence",
+                    time_seconds_sql("st.arrival_time"), time_seconds_sql("st.departure_time"),
+                    delay_sql("arrival_delay", &date, now), delay_sql("departure_delay", &date, now),
+                    cancelled_sql(&date),
+            ).as_str(),
+            vec![],
+        ).await?;
+
+    Ok(stops)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TripInfo {
+    pub trip_id: u32,
+    pub trip_short_name: Option<String>,
+    pub trip_headsign: String,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_long_name: String,
+    pub route_type: RouteType,
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>,
+    pub agency_id: String,
+}
+
+const TRIP_INFO_SELECT: &str = "select t.trip_id, t.trip_short_name, t.trip_headsign,
+    r.route_id, r.route_short_name, r.route_long_name, r.route_type,
+    r.route_color, r.route_text_color, r.agency_id
+    from trip t
+    join route r on r.route_id = t.route_id";
+
+/// Get the trip and route information of a trip
+pub async fn get_trip_info(trip_id: u32, db: &RBatis) -> anyhow::Result<Option<TripInfo>> {
+    let trips: Vec<TripInfo> = db
+        .query_decode(format!("{TRIP_INFO_SELECT} where t.trip_id = {trip_id}").as_str(), vec![])
+        .await?;
+
+    Ok(trips.into_iter().next())
+}
+
+/// Get the trip and route information of multiple trips
+pub async fn get_trip_infos(trip_ids: &[u32], db: &RBatis) -> anyhow::Result<HashMap<u32, TripInfo>> {
+    if trip_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ids = trip_ids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+    let trips: Vec<TripInfo> = db
+        .query_decode(format!("{TRIP_INFO_SELECT} where t.trip_id in ({ids})").as_str(), vec![])
+        .await?;
+
+    Ok(trips.into_iter().map(|trip| (trip.trip_id, trip)).collect())
+}
+
+#[derive(Deserialize)]
+struct TripId {
+    trip_id: u32,
+}
+
+#[derive(Deserialize)]
+struct ServiceId {
+    service_id: u32,
+}
+
+/// Get the services (`Trip.service_id`) that run on `date`
+pub async fn get_active_services(date: NaiveDate, db: &RBatis) -> anyhow::Result<HashSet<u32>> {
+    let services: Vec<ServiceId> = db
+        .query_decode(
+            "select distinct service_id from calendar_date where date = ? and exception_type = 1",
+            vec![rbs::to_value!(date.format("%Y-%m-%d").to_string())],
+        ).await?;
+
+    Ok(services.into_iter().map(|s| s.service_id).collect())
+}
+
+/// Find the trips with this `trip_short_name` (e.g. a train number) that run on `date`
+pub async fn find_trips_by_short_name(
+    trip_short_name: &str, date: NaiveDate, db: &RBatis,
+) -> anyhow::Result<Vec<u32>> {
+    let trips: Vec<TripId> = db
+        .query_decode(
+            "select trip_id from trip
+                where trip_short_name = ?
+                and service_id in (
+                    select service_id from calendar_date where date = ? and exception_type = 1
+                )",
+            vec![
+                rbs::to_value!(trip_short_name),
+                rbs::to_value!(date.format("%Y-%m-%d").to_string()),
+            ],
+        ).await?;
+
+    Ok(trips.into_iter().map(|t| t.trip_id).collect())
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopRow {
+    pub stop_id: String,
+    pub stop_name: String,
+    pub platform_code: Option<String>,
+    pub parent_station: Option<String>,
+    pub stop_lat: f64,
+    pub stop_lon: f64,
+}
+
+/// Get multiple stops or stop areas, keyed by their numeric id
+pub async fn get_stops(stop_ids: &[u32], db: &RBatis) -> anyhow::Result<HashMap<u32, StopRow>> {
+    if stop_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ids = stop_ids.iter()
+        .map(|id| format!("'{id}', 'stoparea:{id}'"))
+        .collect::<Vec<_>>().join(", ");
+    let stops: Vec<StopRow> = db
+        .query_decode(
+            format!("select stop_id, stop_name, platform_code, parent_station, stop_lat, stop_lon
+                from stop where stop_id in ({ids})").as_str(),
+            vec![],
+        ).await?;
+
+    let mut map = HashMap::with_capacity(stops.len());
+    for stop in stops {
+        map.insert(parse_stop_id(&stop.stop_id)?, stop);
+    }
+    Ok(map)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchName {
+    /// Station or stop without a parent station
+    pub stop_id: String,
+    pub name: String,
+}
+
+/// Get all names and codes a station or stop without a parent station can be found by:
+/// stop names and codes (of the stop itself or its platforms), IFF station names
+/// and haltes place codes
+pub async fn get_search_names(db: &RBatis) -> anyhow::Result<Vec<SearchName>> {
+    let names: Vec<SearchName> = db
+        .query_decode(
+            "select coalesce(parent_station, stop_id) as stop_id, stop_name as name from stop
+            union select coalesce(parent_station, stop_id), stop_code from stop
+                where stop_code is not null and stop_code != ''
+            union select coalesce(s.parent_station, s.stop_id), t.station_name from station_transfer t
+                join stop s on s.stop_code = t.station_code
+            union select coalesce(s.parent_station, s.stop_id), p.code from place_transfer p
+                join stop s on s.stop_id = p.stop_id",
+            vec![],
+        ).await?;
+    Ok(names)
+}
+
+#[derive(Deserialize)]
+struct DepartureCount {
+    stop_id: String,
+    departures: u32,
+}
+
+/// Get the number of departures from each station or stop without a parent station,
+/// over all days
+pub async fn get_departure_counts(db: &RBatis) -> anyhow::Result<HashMap<u32, u32>> {
+    let counts: Vec<DepartureCount> = db
+        .query_decode(
+            "select coalesce(s.parent_station, s.stop_id) as stop_id, count(*) as departures
+                from stop_time st join stop s on s.stop_id = st.stop_id
+                where st.pickup_type != 1
+                group by coalesce(s.parent_station, s.stop_id)",
+            vec![],
+        ).await?;
+
+    let mut map = HashMap::with_capacity(counts.len());
+    for DepartureCount { stop_id, departures } in counts {
+        map.insert(parse_stop_id(&stop_id)?, departures);
+    }
+    Ok(map)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ShapePoint {
+    pub shape_pt_lat: f64,
+    pub shape_pt_lon: f64,
+    pub shape_dist_traveled: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct TripShape {
+    shape_id: Option<u32>,
+}
+
+/// Get the shape id of a trip, if it has a shape
+pub async fn get_trip_shape_id(trip_id: u32, db: &RBatis) -> anyhow::Result<Option<u32>> {
+    let trips: Vec<TripShape> = db
+        .query_decode(format!("select shape_id from trip where trip_id = {trip_id}").as_str(), vec![])
+        .await?;
+    Ok(trips.into_iter().next().and_then(|t| t.shape_id))
+}
+
+/// Get all points of a shape, in order
+pub async fn get_shape_points(shape_id: u32, db: &RBatis) -> anyhow::Result<Vec<ShapePoint>> {
+    let points: Vec<ShapePoint> = db
+        .query_decode(
+            format!("select shape_pt_lat, shape_pt_lon, shape_dist_traveled from shape
+                where shape_id = {shape_id} order by shape_pt_sequence").as_str(),
+            vec![],
+        ).await?;
+    Ok(points)
+}
+
+/// Get the simplified shape, if the shapes have been simplified
+pub async fn get_shape_polyline(shape_id: u32, db: &RBatis) -> anyhow::Result<Option<ShapePolyline>> {
+    let polylines: Vec<ShapePolyline> = db
+        .query_decode(
+            format!("select * from shape_polyline where shape_id = {shape_id}").as_str(),
+            vec![],
+        ).await?;
+    Ok(polylines.into_iter().next())
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopDistance {
+    pub stop_id: u32,
+    pub stop_sequence: u32,
+    pub shape_dist_traveled: Option<f64>,
+}
+
+/// Get the distance along the shape of all stops of a trip, ordered by stop sequence
+pub async fn get_trip_stop_distances(trip_id: u32, db: &RBatis) -> anyhow::Result<Vec<StopDistance>> {
+    let stops: Vec<StopDistance> = db
+        .query_decode(
+            format!("select stop_id, stop_sequence, shape_dist_traveled from stop_time
+                where trip_id = {trip_id} order by stop_sequence").as_str(),
+            vec![],
+        ).await?;
+    Ok(stops)
+}
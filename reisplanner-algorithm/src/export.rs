@@ -0,0 +1,120 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use geo::LineString;
+use rbatis::RBatis;
+
+use crate::geometry::{leg_geometry, straight_line};
+use crate::result::{JourneyResult, Leg, StopInfo, VehicleLeg};
+
+pub mod geojson;
+pub mod gpx;
+pub mod ical;
+pub mod kml;
+
+pub use reisplanner_gtfs::utils::TIMEZONE;
+
+/// Convert a GTFS time (seconds since midnight, may exceed 24 hours) on a service date
+/// to a date and time. GTFS times are relative to noon minus 12 hours, which only
+/// differs from midnight on the days daylight saving time starts or ends.
+pub fn service_time(date: NaiveDate, seconds: u32) -> anyhow::Result<DateTime<Tz>> {
+    let noon = TIMEZONE
+        .from_local_datetime(&date.and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()))
+        .single()
+        .ok_or(anyhow::anyhow!("Noon does not exist on {date}"))?;
+    Ok(noon - TimeDelta::hours(12) + TimeDelta::seconds(seconds as i64))
+}
+
+/// Seconds between the start of service date `date` (noon minus 12 hours) and `time`,
+/// the inverse of `service_time`. Exceeds 24 hours after midnight, None before the service date.
+pub fn feed_seconds(date: NaiveDate, time: DateTime<Tz>) -> Option<u32> {
+    let start = service_time(date, 0).ok()?;
+    u32::try_from((time - start).num_seconds()).ok()
+}
+
+/// The current time in the time zone of the feed
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&TIMEZONE)
+}
+
+/// The current date in the time zone of the feed
+pub fn today() -> NaiveDate {
+    now().date_naive()
+}
+
+/// A short description of a vehicle leg, e.g. "IC 2100 to Amsterdam Centraal"
+pub fn leg_name(leg: &VehicleLeg) -> String {
+    let mut name = leg.route_short_name.clone();
+    if let Some(number) = &leg.trip_short_name {
+        name = format!("{name} {number}").trim().to_string();
+    }
+    format!("{name} to {}", leg.headsign)
+}
+
+/// A stop of a journey where a vehicle is boarded or alighted
+pub(crate) struct Waypoint<'a> {
+    pub stop: &'a StopInfo,
+    pub arrival: Option<u32>,
+    pub departure: Option<u32>,
+}
+
+impl Waypoint<'_> {
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(platform) = &self.stop.platform_code {
+            parts.push(format!("Platform {platform}"));
+        }
+        if let Some(arrival) = self.arrival {
+            parts.push(format!("arrival {}", clock_time(arrival)));
+        }
+        if let Some(departure) = self.departure {
+            parts.push(format!("departure {}", clock_time(departure)));
+        }
+        parts.join(", ")
+    }
+}
+
+/// The boarded and alighted stops of a journey, in order. If a vehicle departs
+/// from the stop where the previous one arrived, both are combined.
+pub(crate) fn waypoints(journey: &JourneyResult) -> Vec<Waypoint<'_>> {
+    let mut waypoints: Vec<Waypoint> = Vec::new();
+    for leg in &journey.legs {
+        let Leg::Vehicle(vehicle) = leg else { continue };
+        match waypoints.last_mut() {
+            Some(last) if last.stop.stop_id == vehicle.from.stop_id => {
+                last.departure = Some(vehicle.departure);
+            }
+            _ => waypoints.push(Waypoint { stop: &vehicle.from, arrival: None, departure: Some(vehicle.departure) }),
+        }
+        waypoints.push(Waypoint { stop: &vehicle.to, arrival: Some(vehicle.arrival), departure: None });
+    }
+    waypoints
+}
+
+/// The geometry of all vehicle legs and transfers of a journey
+pub(crate) async fn leg_lines<'a>(
+    journey: &'a JourneyResult,
+    db: &RBatis,
+) -> anyhow::Result<Vec<(&'a Leg, LineString)>> {
+    let mut lines = Vec::new();
+    for leg in &journey.legs {
+        match leg {
+            Leg::Vehicle(vehicle) => lines.push((leg, leg_geometry(vehicle, db).await?)),
+            Leg::Transfer(transfer) => lines.push((leg, straight_line(&transfer.from, &transfer.to))),
+            Leg::Via(_) => {}
+        }
+    }
+    Ok(lines)
+}
+
+/// Time of day as HH:MM
+pub fn clock_time(seconds: u32) -> String {
+    format!("{:02}:{:02}", seconds / 3600 % 24, seconds % 3600 / 60)
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
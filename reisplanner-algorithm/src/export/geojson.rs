@@ -0,0 +1,75 @@
+use ::geojson::{Feature, FeatureCollection, Geometry, JsonObject, JsonValue, Value};
+use rbatis::RBatis;
+
+use crate::geometry::{leg_geometry, straight_line};
+use crate::result::{JourneyResult, Leg, StopInfo};
+use crate::utils::seconds_to_hms;
+
+/// Export a journey as a GeoJSON feature collection, with a line for each leg
+/// and a point for each stop where a vehicle is boarded or alighted.
+/// Vehicle legs follow the shape of the trip when available.
+pub async fn journey_to_geojson(journey: &JourneyResult, db: &RBatis) -> anyhow::Result<FeatureCollection> {
+    let mut features = Vec::new();
+
+    for leg in &journey.legs {
+        match leg {
+            Leg::Vehicle(vehicle) => {
+                let line = leg_geometry(vehicle, db).await?;
+                let mut properties = JsonObject::new();
+                properties.insert("type".to_string(), "vehicle".into());
+                properties.insert("from".to_string(), vehicle.from.name.clone().into());
+                properties.insert("to".to_string(), vehicle.to.name.clone().into());
+                properties.insert("departure".to_string(), seconds_to_hms(vehicle.departure).into());
+                properties.insert("arrival".to_string(), seconds_to_hms(vehicle.arrival).into());
+                properties.insert("trip_id".to_string(), vehicle.trip_id.into());
+                properties.insert("trip_short_name".to_string(), vehicle.trip_short_name.clone().into());
+                properties.insert("headsign".to_string(), vehicle.headsign.clone().into());
+                properties.insert("route_short_name".to_string(), vehicle.route_short_name.clone().into());
+                properties.insert("route_type".to_string(), (vehicle.route_type as u8).into());
+                properties.insert("agency_id".to_string(), vehicle.agency_id.clone().into());
+                properties.insert("route_color".to_string(), vehicle.route_color.clone().into());
+                properties.insert("route_text_color".to_string(), vehicle.route_text_color.clone().into());
+                features.push(feature(Geometry::from(&line), properties));
+
+                features.push(stop_feature(&vehicle.from, Some(vehicle.departure), "departure"));
+                features.push(stop_feature(&vehicle.to, Some(vehicle.arrival), "arrival"));
+            }
+            Leg::Transfer(transfer) => {
+                let mut properties = JsonObject::new();
+                properties.insert("type".to_string(), "transfer".into());
+                properties.insert("from".to_string(), transfer.from.name.clone().into());
+                properties.insert("to".to_string(), transfer.to.name.clone().into());
+                properties.insert("duration".to_string(), transfer.duration.into());
+                let line = straight_line(&transfer.from, &transfer.to);
+                features.push(feature(Geometry::from(&line), properties));
+            }
+            Leg::Via(stop) => {
+                features.push(stop_feature(stop, None, "via"));
+            }
+        }
+    }
+
+    Ok(FeatureCollection {
+        features,
+        bbox: None,
+        foreign_members: None,
+    })
+}
+
+fn stop_feature(stop: &StopInfo, time: Option<u32>, kind: &str) -> Feature {
+    let mut properties = JsonObject::new();
+    properties.insert("type".to_string(), kind.into());
+    properties.insert("stop_id".to_string(), stop.stop_id.into());
+    properties.insert("name".to_string(), stop.name.clone().into());
+    properties.insert("platform_code".to_string(), stop.platform_code.clone().into());
+    properties.insert("time".to_string(), time.map(seconds_to_hms).map_or(JsonValue::Null, Into::into));
+    feature(Geometry::new(Value::Point(vec![stop.lon, stop.lat])), properties)
+}
+
+fn feature(geometry: Geometry, properties: JsonObject) -> Feature {
+    Feature {
+        geometry: Some(geometry),
+        properties: Some(properties),
+        ..Default::default()
+    }
+}
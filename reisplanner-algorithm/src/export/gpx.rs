@@ -0,0 +1,75 @@
+use std::fmt::Write;
+
+use chrono::{NaiveDate, SecondsFormat, Utc};
+use rbatis::RBatis;
+
+use crate::export::{escape_xml, leg_lines, leg_name, service_time, waypoints};
+use crate::result::{JourneyResult, Leg};
+
+/// Export a journey on `date` as GPX, with a waypoint for each boarded or alighted stop
+/// and a track with a segment for each vehicle leg and transfer.
+/// Vehicle legs follow the shape of the trip when available.
+pub async fn journey_to_gpx(journey: &JourneyResult, date: NaiveDate, db: &RBatis) -> anyhow::Result<String> {
+    let waypoints = waypoints(journey);
+    let name = match (waypoints.first(), waypoints.last()) {
+        (Some(first), Some(last)) => format!("{} - {}", first.stop.name, last.stop.name),
+        _ => "Journey".to_string(),
+    };
+    let time = |seconds: u32| -> anyhow::Result<String> {
+        Ok(service_time(date, seconds)?
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true))
+    };
+
+    let mut gpx = String::new();
+    writeln!(gpx, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(gpx, r#"<gpx version="1.1" creator="reisplanner" xmlns="http://www.topografix.com/GPX/1/1">"#)?;
+    writeln!(gpx, "  <metadata>")?;
+    writeln!(gpx, "    <name>{}</name>", escape_xml(&name))?;
+    if let Some(departure) = journey.departure {
+        writeln!(gpx, "    <time>{}</time>", time(departure)?)?;
+    }
+    writeln!(gpx, "  </metadata>")?;
+
+    for waypoint in &waypoints {
+        writeln!(gpx, r#"  <wpt lat="{}" lon="{}">"#, waypoint.stop.lat, waypoint.stop.lon)?;
+        if let Some(seconds) = waypoint.departure.or(waypoint.arrival) {
+            writeln!(gpx, "    <time>{}</time>", time(seconds)?)?;
+        }
+        writeln!(gpx, "    <name>{}</name>", escape_xml(&waypoint.stop.name))?;
+        writeln!(gpx, "    <desc>{}</desc>", escape_xml(&waypoint.description()))?;
+        writeln!(gpx, "  </wpt>")?;
+    }
+
+    writeln!(gpx, "  <trk>")?;
+    writeln!(gpx, "    <name>{}</name>", escape_xml(&name))?;
+    for (leg, line) in leg_lines(journey, db).await? {
+        // Only the first and last point of a vehicle leg have a known time
+        let times = match leg {
+            Leg::Vehicle(vehicle) => Some((time(vehicle.departure)?, time(vehicle.arrival)?)),
+            _ => None,
+        };
+        match leg {
+            Leg::Vehicle(vehicle) => writeln!(gpx, "    <!-- {} -->", escape_xml(&leg_name(vehicle)))?,
+            _ => writeln!(gpx, "    <!-- Transfer -->")?,
+        }
+        writeln!(gpx, "    <trkseg>")?;
+        let last = line.0.len().saturating_sub(1);
+        for (i, coord) in line.0.iter().enumerate() {
+            let point_time = match &times {
+                Some((departure, _)) if i == 0 => Some(departure),
+                Some((_, arrival)) if i == last => Some(arrival),
+                _ => None,
+            };
+            match point_time {
+                Some(t) => writeln!(gpx, r#"      <trkpt lat="{}" lon="{}"><time>{t}</time></trkpt>"#, coord.y, coord.x)?,
+                None => writeln!(gpx, r#"      <trkpt lat="{}" lon="{}"/>"#, coord.y, coord.x)?,
+            }
+        }
+        writeln!(gpx, "    </trkseg>")?;
+    }
+    writeln!(gpx, "  </trk>")?;
+    writeln!(gpx, "</gpx>")?;
+
+    Ok(gpx)
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::export::{clock_time, leg_name, service_time};
+use crate::result::{JourneyResult, Leg, StopInfo};
+
+/// Export a journey on `date` as an iCalendar file, with an event for each vehicle leg.
+/// Times are converted from Europe/Amsterdam to UTC.
+pub fn journey_to_ical(journey: &JourneyResult, date: NaiveDate) -> anyhow::Result<String> {
+    let now = format_time(Utc::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//reisplanner//journey//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+
+    for leg in &journey.legs {
+        let Leg::Vehicle(vehicle) = leg else { continue };
+        let description = format!(
+            "Departure: {} {}\nArrival: {} {}",
+            clock_time(vehicle.departure), stop_text(&vehicle.from),
+            clock_time(vehicle.arrival), stop_text(&vehicle.to),
+        );
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{}-{}@reisplanner", date.format("%Y%m%d"), vehicle.trip_id, vehicle.from.stop_id),
+            format!("DTSTAMP:{now}"),
+            format!("DTSTART:{}", format_time(service_time(date, vehicle.departure)?)),
+            format!("DTEND:{}", format_time(service_time(date, vehicle.arrival)?)),
+            format!("SUMMARY:{}", escape_text(&leg_name(vehicle))),
+            format!("LOCATION:{}", escape_text(&stop_text(&vehicle.from))),
+            format!("DESCRIPTION:{}", escape_text(&description)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    Ok(lines.iter().map(|line| fold_line(line) + "\r\n").collect())
+}
+
+fn stop_text(stop: &StopInfo) -> String {
+    match &stop.platform_code {
+        Some(platform) => format!("{}, platform {platform}", stop.name),
+        None => stop.name.clone(),
+    }
+}
+
+fn format_time<Tz: chrono::TimeZone>(time: DateTime<Tz>) -> String {
+    time.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value (RFC 5545 section 3.3.11)
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold lines longer than 75 octets (RFC 5545 section 3.1), without splitting characters
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the length
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
@@ -0,0 +1,72 @@
+use std::fmt::Write;
+
+use chrono::{NaiveDate, SecondsFormat};
+use rbatis::RBatis;
+
+use crate::export::{clock_time, escape_xml, leg_lines, leg_name, service_time, waypoints};
+use crate::result::{JourneyResult, Leg};
+
+/// Export a journey on `date` as KML, with a placemark for each boarded or alighted stop
+/// and a line for each vehicle leg and transfer.
+/// Vehicle legs follow the shape of the trip when available.
+pub async fn journey_to_kml(journey: &JourneyResult, date: NaiveDate, db: &RBatis) -> anyhow::Result<String> {
+    let waypoints = waypoints(journey);
+    let name = match (waypoints.first(), waypoints.last()) {
+        (Some(first), Some(last)) => format!("{} - {}", first.stop.name, last.stop.name),
+        _ => "Journey".to_string(),
+    };
+    let time = |seconds: u32| -> anyhow::Result<String> {
+        Ok(service_time(date, seconds)?.to_rfc3339_opts(SecondsFormat::Secs, false))
+    };
+
+    let mut kml = String::new();
+    writeln!(kml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(kml, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(kml, "  <Document>")?;
+    writeln!(kml, "    <name>{}</name>", escape_xml(&name))?;
+    writeln!(kml, r#"    <Style id="vehicle"><LineStyle><color>ffc06000</color><width>4</width></LineStyle></Style>"#)?;
+    writeln!(kml, r#"    <Style id="transfer"><LineStyle><color>ff808080</color><width>2</width></LineStyle></Style>"#)?;
+
+    for waypoint in &waypoints {
+        writeln!(kml, "    <Placemark>")?;
+        writeln!(kml, "      <name>{}</name>", escape_xml(&waypoint.stop.name))?;
+        writeln!(kml, "      <description>{}</description>", escape_xml(&waypoint.description()))?;
+        if let Some(seconds) = waypoint.departure.or(waypoint.arrival) {
+            writeln!(kml, "      <TimeStamp><when>{}</when></TimeStamp>", time(seconds)?)?;
+        }
+        writeln!(kml, "      <Point><coordinates>{},{}</coordinates></Point>", waypoint.stop.lon, waypoint.stop.lat)?;
+        writeln!(kml, "    </Placemark>")?;
+    }
+
+    for (leg, line) in leg_lines(journey, db).await? {
+        writeln!(kml, "    <Placemark>")?;
+        match leg {
+            Leg::Vehicle(vehicle) => {
+                writeln!(kml, "      <name>{}</name>", escape_xml(&leg_name(vehicle)))?;
+                let description = format!(
+                    "{} {} - {} {}",
+                    clock_time(vehicle.departure), vehicle.from.name,
+                    clock_time(vehicle.arrival), vehicle.to.name,
+                );
+                writeln!(kml, "      <description>{}</description>", escape_xml(&description))?;
+                writeln!(kml, "      <TimeSpan><begin>{}</begin><end>{}</end></TimeSpan>",
+                         time(vehicle.departure)?, time(vehicle.arrival)?)?;
+                writeln!(kml, "      <styleUrl>#vehicle</styleUrl>")?;
+            }
+            _ => {
+                writeln!(kml, "      <name>Transfer</name>")?;
+                writeln!(kml, "      <styleUrl>#transfer</styleUrl>")?;
+            }
+        }
+        let coordinates: Vec<String> = line.0.iter()
+            .map(|c| format!("{},{}", c.x, c.y))
+            .collect();
+        writeln!(kml, "      <LineString><coordinates>{}</coordinates></LineString>", coordinates.join(" "))?;
+        writeln!(kml, "    </Placemark>")?;
+    }
+
+    writeln!(kml, "  </Document>")?;
+    writeln!(kml, "</kml>")?;
+
+    Ok(kml)
+}
@@ -0,0 +1,286 @@
+use chrono::NaiveDate;
+use rbatis::RBatis;
+
+use reisplanner_gtfs::gtfs::types::{AllowedType, CalendarDate, DelayRecord, LocationType, PickupType, Route, RouteType, Shape, ShapePolyline, Stop, StopTime, Trip, WheelchairBoarding};
+use reisplanner_gtfs::utils::{naive_date_to_date, TimeTuple};
+use reisplanner_data::types::{PlaceTransfer, StationTransfer};
+use reisplanner_utils::database::sync_table;
+
+pub const UTRECHT: u32 = 1000;
+pub const UTRECHT_PLATFORM_5: u32 = 1001;
+pub const UTRECHT_PLATFORM_7: u32 = 1002;
+pub const AMSTERDAM: u32 = 2000;
+pub const DEN_HAAG: u32 = 3000;
+pub const DEN_BOSCH: u32 = 4000;
+pub const LIEGE: u32 = 5000;
+
+fn station(stop_id: u32, stop_code: &str, stop_name: &str, stop_lat: f64, stop_lon: f64) -> Stop {
+    Stop {
+        stop_id: format!("stoparea:{stop_id}"),
+        stop_code: Some(stop_code.to_string()),
+        stop_name: stop_name.to_string(),
+        stop_lat,
+        stop_lon,
+        location_type: LocationType::Station,
+        parent_station: None,
+        stop_timezone: None,
+        wheelchair_boarding: None,
+        platform_code: None,
+        zone_id: None,
+    }
+}
+
+fn platform(parent: &Stop, stop_id: u32, platform_code: &str) -> Stop {
+    Stop {
+        stop_id: stop_id.to_string(),
+        location_type: LocationType::Stop,
+        parent_station: Some(parent.stop_id.clone()),
+        platform_code: Some(platform_code.to_string()),
+        stop_code: None,
+        wheelchair_boarding: None,
+        ..parent.clone()
+    }
+}
+
+fn route(route_id: u32, route_short_name: &str, route_type: RouteType, agency_id: &str) -> Route {
+    Route {
+        route_id,
+        agency_id: agency_id.to_string(),
+        route_short_name: route_short_name.to_string(),
+        route_long_name: String::new(),
+        route_desc: None,
+        route_type,
+        route_color: None,
+        route_text_color: None,
+        route_url: None,
+    }
+}
+
+fn trip(trip_id: u32, route_id: u32, trip_headsign: &str, trip_short_name: &str) -> Trip {
+    Trip {
+        route_id,
+        service_id: 1,
+        trip_id,
+        realtime_trip_id: trip_id.to_string(),
+        trip_headsign: trip_headsign.to_string(),
+        trip_short_name: Some(trip_short_name.to_string()),
+        trip_long_name: None,
+        direction_id: 0,
+        block_id: None,
+        shape_id: None,
+        wheelchair_accessible: None,
+        bikes_allowed: None,
+        delay: None,
+    }
+}
+
+/// Stop times of a trip, given as (stop_id, arrival, departure)
+fn stop_times(trip_id: u32, stops: &[(u32, TimeTuple, TimeTuple)]) -> Vec<StopTime> {
+    stops.iter().enumerate()
+        .map(|(i, &(stop_id, arrival_time, departure_time))| StopTime {
+            trip_id,
+            stop_sequence: i as u32 + 1,
+            stop_id,
+            stop_headsign: None,
+            arrival_time,
+            departure_time,
+            // Boarding is not possible at the last stop, alighting not at the first
+            pickup_type: if i + 1 == stops.len() { PickupType::NotAvailable } else { PickupType::Regular },
+            drop_off_type: if i == 0 { PickupType::NotAvailable } else { PickupType::Regular },
+            timepoint: 1,
+            shape_dist_traveled: None,
+            fare_units_traveled: None,
+            id: None,
+            arrival_delay: None,
+            departure_delay: None,
+        })
+        .collect()
+}
+
+/// An in-memory database with a small network, where all trips run on `date`.
+/// Trips 100, 104 and 105 and all stations except Amsterdam are wheelchair accessible,
+/// but Utrecht platform 7 is not. The trips:
+///  - trip 100: Den Haag 09:20 - Utrecht platform 5 10:00 - Amsterdam 10:27, with a shape via Leiden
+///  - trip 101: Utrecht platform 7 10:30 - Amsterdam 10:57, departs 3 minutes late
+///  - trip 102: Utrecht platform 5 10:45 - Amsterdam 11:12, cancelled
+///  - trip 103: Amsterdam 09:33 - Utrecht platform 7 10:00
+///  - trip 104: Den Haag 11:00 - 's-Hertogenbosch 12:00, where transferring takes 5 minutes
+///  - trip 105: 's-Hertogenbosch 12:40 - Liège 13:40, operated by NMBS
+///  - trip 106: 's-Hertogenbosch 12:10 - Liège 13:10, operated by NMBS, not wheelchair accessible
+///  - trip 107: 's-Hertogenbosch 12:20 - Liège 14:30, a bus operated by Arriva
+pub async fn fixture_db(date: NaiveDate) -> anyhow::Result<RBatis> {
+    let db = RBatis::new();
+    db.init(rbdc_sqlite::driver::SqliteDriver {}, "sqlite://:memory:")?;
+    // Every connection would have its own in-memory database
+    db.get_pool()?.set_max_open_conns(1).await;
+    sync_table::<CalendarDate>(&db, "calendar_date").await?;
+    sync_table::<DelayRecord>(&db, "delay_record").await?;
+    sync_table::<Route>(&db, "route").await?;
+    sync_table::<Shape>(&db, "shape").await?;
+    sync_table::<ShapePolyline>(&db, "shape_polyline").await?;
+    sync_table::<Stop>(&db, "stop").await?;
+    sync_table::<StopTime>(&db, "stop_time").await?;
+    sync_table::<Trip>(&db, "trip").await?;
+    sync_table::<StationTransfer>(&db, "station_transfer").await?;
+    sync_table::<PlaceTransfer>(&db, "place_transfer").await?;
+
+    // All stations except Amsterdam are wheelchair accessible, platforms inherit this
+    let accessible = |stop: Stop| Stop { wheelchair_boarding: Some(WheelchairBoarding::SomePossible), ..stop };
+    let utrecht = accessible(station(UTRECHT, "UT", "Utrecht Centraal", 52.0894, 5.1100));
+    let amsterdam = station(AMSTERDAM, "ASD", "Amsterdam Centraal", 52.3789, 4.9003);
+    let den_haag = accessible(station(DEN_HAAG, "GVC", "Den Haag Centraal", 52.0808, 4.3249));
+    let den_bosch = accessible(station(DEN_BOSCH, "HT", "’s-Hertogenbosch", 51.6906, 5.2936));
+    let liege = accessible(station(LIEGE, "LG", "Liège-Guillemins", 50.6245, 5.5667));
+    let stops = [
+        platform(&utrecht, UTRECHT_PLATFORM_5, "5"),
+        Stop {
+            wheelchair_boarding: Some(WheelchairBoarding::NotPossible),
+            ..platform(&utrecht, UTRECHT_PLATFORM_7, "7")
+        },
+        platform(&amsterdam, 2001, "2"),
+        platform(&den_haag, 3001, "1"),
+        platform(&den_bosch, 4001, "3"),
+        platform(&liege, 5001, "4"),
+        utrecht, amsterdam, den_haag, den_bosch, liege,
+    ];
+    Stop::insert_batch(&db, &stops, stops.len() as u64).await?;
+
+    let routes = [
+        route(10, "IC", RouteType::Train, "NS"),
+        route(20, "SPR", RouteType::Train, "NS"),
+        route(30, "EC", RouteType::Train, "NMBS"),
+        route(40, "Bus", RouteType::Bus, "ARRIVA"),
+    ];
+    Route::insert_batch(&db, &routes, routes.len() as u64).await?;
+
+    let accessible = |trip: Trip| Trip { wheelchair_accessible: Some(AllowedType::Allowed), ..trip };
+    let trips = [
+        accessible(Trip { shape_id: Some(1), ..trip(100, 10, "Amsterdam Centraal", "2100") }),
+        trip(101, 20, "Amsterdam Centraal", "4800"),
+        trip(102, 10, "Amsterdam Centraal", "2200"),
+        trip(103, 20, "Utrecht Centraal", "4900"),
+        accessible(trip(104, 10, "’s-Hertogenbosch", "3500")),
+        accessible(trip(105, 30, "Liège-Guillemins", "9400")),
+        Trip { wheelchair_accessible: Some(AllowedType::NotAllowed), ..trip(106, 30, "Liège-Guillemins", "9402") },
+        trip(107, 40, "Liège-Guillemins", "300"),
+    ];
+    Trip::insert_batch(&db, &trips, trips.len() as u64).await?;
+
+    let mut times = Vec::new();
+    times.extend(stop_times(100, &[
+        (3001, TimeTuple(9, 20, 0), TimeTuple(9, 20, 0)),
+        (UTRECHT_PLATFORM_5, TimeTuple(10, 0, 0), TimeTuple(10, 2, 0)),
+        (2001, TimeTuple(10, 27, 0), TimeTuple(10, 27, 0)),
+    ]));
+    times.extend(stop_times(101, &[
+        (UTRECHT_PLATFORM_7, TimeTuple(10, 30, 0), TimeTuple(10, 30, 0)),
+        (2001, TimeTuple(10, 57, 0), TimeTuple(10, 57, 0)),
+    ]));
+    times.extend(stop_times(102, &[
+        (UTRECHT_PLATFORM_5, TimeTuple(10, 45, 0), TimeTuple(10, 45, 0)),
+        (2001, TimeTuple(11, 12, 0), TimeTuple(11, 12, 0)),
+    ]));
+    times.extend(stop_times(103, &[
+        (2001, TimeTuple(9, 33, 0), TimeTuple(9, 33, 0)),
+        (UTRECHT_PLATFORM_7, TimeTuple(10, 0, 0), TimeTuple(10, 0, 0)),
+    ]));
+    times.extend(stop_times(104, &[
+        (3001, TimeTuple(11, 0, 0), TimeTuple(11, 0, 0)),
+        (4001, TimeTuple(12, 0, 0), TimeTuple(12, 0, 0)),
+    ]));
+    // Trip 106 departs before trip 105 of the same route
+    times.extend(stop_times(105, &[
+        (4001, TimeTuple(12, 40, 0), TimeTuple(12, 40, 0)),
+        (5001, TimeTuple(13, 40, 0), TimeTuple(13, 40, 0)),
+    ]));
+    times.extend(stop_times(106, &[
+        (4001, TimeTuple(12, 10, 0), TimeTuple(12, 10, 0)),
+        (5001, TimeTuple(13, 10, 0), TimeTuple(13, 10, 0)),
+    ]));
+    times.extend(stop_times(107, &[
+        (4001, TimeTuple(12, 20, 0), TimeTuple(12, 20, 0)),
+        (5001, TimeTuple(14, 30, 0), TimeTuple(14, 30, 0)),
+    ]));
+    // Ids in order of trip and stop sequence, as generated when parsing the GTFS
+    for (i, stop_time) in times.iter_mut().enumerate() {
+        stop_time.id = Some(i as i32 + 1);
+    }
+    for (stop_time, distance) in times.iter_mut().zip([0.0, 60000.0, 95000.0]) {
+        stop_time.shape_dist_traveled = Some(distance);
+    }
+    // Realtime information, in `stop_time` and archived per service date as the realtime parser does
+    times[3].departure_delay = Some(180);
+    StopTime::insert_batch(&db, &times, times.len() as u64).await?;
+    let delay = &times[3];
+    let mut records = vec![DelayRecord {
+        service_date: naive_date_to_date(date),
+        trip_id: delay.trip_id,
+        stop_sequence: delay.stop_sequence,
+        route_id: 20,
+        stop_id: delay.stop_id,
+        scheduled_arrival: delay.arrival_time.into(),
+        scheduled_departure: delay.departure_time.into(),
+        predicted_departure: Some(u32::from(delay.departure_time) + 180),
+        departure_delay: Some(180),
+        ..Default::default()
+    }];
+    records.extend(times.iter()
+        .filter(|s| s.trip_id == 102)
+        .map(|s| DelayRecord {
+            service_date: naive_date_to_date(date),
+            trip_id: s.trip_id,
+            stop_sequence: s.stop_sequence,
+            route_id: 10,
+            stop_id: s.stop_id,
+            scheduled_arrival: s.arrival_time.into(),
+            scheduled_departure: s.departure_time.into(),
+            predicted_arrival: None,
+            predicted_departure: None,
+            arrival_delay: None,
+            departure_delay: None,
+            cancelled: true,
+            ..Default::default()
+        }));
+    DelayRecord::insert_batch(&db, &records, records.len() as u64).await?;
+
+    let shape: Vec<Shape> = [
+        (52.0808, 4.3249, 0.0),
+        (52.1661, 4.4819, 30000.0),
+        (52.0894, 5.1100, 60000.0),
+        (52.3789, 4.9003, 95000.0),
+    ].into_iter().enumerate()
+        .map(|(i, (shape_pt_lat, shape_pt_lon, distance))| Shape {
+            shape_id: 1,
+            shape_pt_sequence: i as u32 + 1,
+            shape_pt_lat,
+            shape_pt_lon,
+            shape_dist_traveled: Some(distance),
+        })
+        .collect();
+    Shape::insert_batch(&db, &shape, shape.len() as u64).await?;
+
+    let transfers = [
+        StationTransfer {
+            station_code: "GVC".to_string(),
+            transfer_time: 3,
+            station_name: "'s-Gravenhage".to_string(),
+        },
+        StationTransfer {
+            station_code: "HT".to_string(),
+            transfer_time: 5,
+            station_name: "'s-Hertogenbosch".to_string(),
+        },
+    ];
+    StationTransfer::insert_batch(&db, &transfers, transfers.len() as u64).await?;
+    let place = PlaceTransfer { code: "UTCS".to_string(), stop_id: UTRECHT_PLATFORM_5.to_string() };
+    PlaceTransfer::insert(&db, &place).await?;
+
+    let calendar = CalendarDate {
+        service_id: 1,
+        date: naive_date_to_date(date),
+        exception_type: Default::default(),
+    };
+    CalendarDate::insert(&db, &calendar).await?;
+
+    Ok(db)
+}
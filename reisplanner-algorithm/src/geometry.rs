@@ -0,0 +1,117 @@
+use geo::{Coord, LineString};
+use rbatis::RBatis;
+
+use reisplanner_gtfs::shapes::encode_polyline;
+
+use crate::database::queries::{get_shape_points, get_shape_polyline, get_trip_shape_id, get_trip_stop_distances, StopDistance};
+use crate::result::{StopInfo, VehicleLeg};
+
+/// The geometry of a vehicle leg, using the shape of the trip between the boarded
+/// and alighted stops. Falls back to a straight line if the trip has no (usable) shape.
+pub async fn leg_geometry(leg: &VehicleLeg, db: &RBatis) -> anyhow::Result<LineString> {
+    let shape = shape_between_stops(
+        leg.trip_id, |s| s.stop_id == leg.from.stop_id, |s| s.stop_id == leg.to.stop_id, db,
+    ).await?;
+    if shape.len() < 2 {
+        return Ok(straight_line(&leg.from, &leg.to));
+    }
+    Ok(LineString(shape))
+}
+
+/// The encoded polyline of the part of a trip between two stop sequences,
+/// or None if the trip has no (usable) shape
+pub async fn leg_polyline(
+    trip_id: u32,
+    from_sequence: u32,
+    to_sequence: u32,
+    db: &RBatis,
+) -> anyhow::Result<Option<String>> {
+    let shape = shape_between_stops(
+        trip_id, |s| s.stop_sequence == from_sequence, |s| s.stop_sequence == to_sequence, db,
+    ).await?;
+    if shape.len() < 2 {
+        return Ok(None);
+    }
+    Ok(Some(encode_polyline(shape)?))
+}
+
+/// The part of the shape of a trip between the first stop matching `from`
+/// and the next stop matching `to`. Empty if the trip has no shape or does not visit the stops.
+async fn shape_between_stops(
+    trip_id: u32,
+    from: impl Fn(&StopDistance) -> bool,
+    to: impl Fn(&StopDistance) -> bool,
+    db: &RBatis,
+) -> anyhow::Result<Vec<Coord>> {
+    let Some(shape_id) = get_trip_shape_id(trip_id, db).await? else {
+        return Ok(Vec::new());
+    };
+    let stops = get_trip_stop_distances(trip_id, db).await?;
+    let Some(from) = stops.iter().position(from) else {
+        return Ok(Vec::new());
+    };
+    match stops[from..].iter().find(|s| to(s)) {
+        Some(to) => shape_between(shape_id, &stops[from], to, db).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The part of a shape between two stops, using the simplified shape if available
+async fn shape_between(
+    shape_id: u32,
+    from: &StopDistance,
+    to: &StopDistance,
+    db: &RBatis,
+) -> anyhow::Result<Vec<Coord>> {
+    let (Some(from), Some(to)) = (from.shape_dist_traveled, to.shape_dist_traveled) else {
+        return Ok(Vec::new());
+    };
+    let points = match get_shape_polyline(shape_id, db).await? {
+        Some(polyline) => polyline.points()?,
+        None => get_shape_points(shape_id, db).await?
+            .into_iter()
+            .map(|p| (Coord { x: p.shape_pt_lon, y: p.shape_pt_lat }, p.shape_dist_traveled))
+            .collect(),
+    };
+    Ok(cut_shape(&points, from, to))
+}
+
+pub fn straight_line(from: &StopInfo, to: &StopInfo) -> LineString {
+    LineString(vec![
+        Coord { x: from.lon, y: from.lat },
+        Coord { x: to.lon, y: to.lat },
+    ])
+}
+
+/// Cut the part between the distances `from` and `to` out of a shape,
+/// interpolating the first and last point. Points without a distance are ignored.
+pub fn cut_shape(points: &[(Coord, Option<f64>)], from: f64, to: f64) -> Vec<Coord> {
+    let points: Vec<(Coord, f64)> = points.iter()
+        .filter_map(|&(coord, distance)| distance.map(|d| (coord, d)))
+        .collect();
+
+    let mut result = Vec::new();
+    for pair in points.windows(2) {
+        let ((a, a_dist), (b, b_dist)) = (pair[0], pair[1]);
+        if b_dist < from || a_dist > to || b_dist <= a_dist {
+            continue;
+        }
+        if result.is_empty() {
+            result.push(interpolate(a, a_dist, b, b_dist, from.max(a_dist)));
+        }
+        if b_dist >= to {
+            result.push(interpolate(a, a_dist, b, b_dist, to));
+            break;
+        }
+        result.push(b);
+    }
+    result
+}
+
+fn interpolate(a: Coord, a_dist: f64, b: Coord, b_dist: f64, dist: f64) -> Coord {
+    let fraction = (dist - a_dist) / (b_dist - a_dist);
+    Coord {
+        x: a.x + (b.x - a.x) * fraction,
+        y: a.y + (b.y - a.y) * fraction,
+    }
+}
@@ -1,9 +1,19 @@
 #[cfg(test)]
 mod tests;
 #[allow(dead_code)]
-mod database;
+pub mod database;
 mod utils;
 mod getters;
 pub mod algorithms;
-mod types;
+pub mod types;
+pub mod reliability;
+pub mod board;
+pub mod trip_details;
+pub mod result;
+pub mod geometry;
+pub mod export;
+pub mod search;
+pub mod spatial;
+#[cfg(any(test, feature = "fixture"))]
+pub mod fixture;
 // TODO remove allow(dead_code)
\ No newline at end of file
@@ -0,0 +1,116 @@
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use crate::database::queries::{get_arrival_delays, get_trip_infos, StopDelay};
+use crate::types::{Journey, JourneyPart};
+
+/// Minimum number of historical delays needed to use a delay distribution
+const MIN_SAMPLES: usize = 10;
+
+/// Risk of missing a transfer
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum RiskLevel {
+    /// Not enough historical data
+    #[default]
+    Unknown,
+    /// At least 95% chance to make the transfer
+    Low,
+    /// At least 80% chance to make the transfer
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn from_probability(probability: f64) -> Self {
+        if probability >= 0.95 {
+            RiskLevel::Low
+        } else if probability >= 0.8 {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::High
+        }
+    }
+}
+
+/// Probability that the arrival delay is at most `slack` seconds,
+/// or None if there are no delays to base it on
+pub fn transfer_probability(delays: &[i32], slack: i64) -> Option<f64> {
+    if delays.is_empty() {
+        return None;
+    }
+    let made = delays.iter().filter(|&&d| d as i64 <= slack).count();
+    Some(made as f64 / delays.len() as f64)
+}
+
+/// Get the historical arrival delays of a trip (of route `route_id`) at a stop from `delays`.
+/// Falls back to the delays of the route and then of all trips at the stop
+/// if there is not enough data.
+pub fn delay_distribution(trip_id: u32, route_id: Option<u32>, stop_id: u32, delays: &[StopDelay]) -> Option<Vec<i32>> {
+    let at_stop = |filter: &dyn Fn(&StopDelay) -> bool| -> Vec<i32> {
+        delays.iter()
+            .filter(|d| d.stop_id == stop_id && filter(d))
+            .map(|d| d.arrival_delay)
+            .collect()
+    };
+    let delays = at_stop(&|d| d.trip_id == trip_id);
+    if delays.len() >= MIN_SAMPLES { return Some(delays); }
+    let delays = at_stop(&|d| Some(d.route_id) == route_id);
+    if delays.len() >= MIN_SAMPLES { return Some(delays); }
+    let delays = at_stop(&|_| true);
+    if delays.len() >= MIN_SAMPLES { return Some(delays); }
+    None
+}
+
+/// Annotate each transfer in the journey with the risk of missing the next trip,
+/// and the journey with the probability of making all transfers.
+/// The slack of a transfer is the time between arriving and departing,
+/// minus the time needed for the transfer itself.
+/// The reliability of the journey is unknown if any transfer has too little data.
+pub async fn annotate_reliability(journey: &mut Journey, db: &RBatis) -> anyhow::Result<()> {
+    // The transfers as (index in the journey, trip and stop we arrive with, slack)
+    let mut transfers = Vec::new();
+    for (i, part) in journey.parts.iter().enumerate() {
+        let JourneyPart::Transfer(transfer) = part else { continue };
+        // The trips around the transfer, a via stop may be in between
+        let previous = journey.parts[..i].iter().rev()
+            .take_while(|p| !matches!(p, JourneyPart::Transfer(_)))
+            .find_map(JourneyPart::vehicle);
+        let next = journey.parts[i + 1..].iter()
+            .take_while(|p| !matches!(p, JourneyPart::Transfer(_)))
+            .find_map(JourneyPart::vehicle);
+        let (Some(previous), Some(next)) = (previous, next) else { continue };
+
+        let slack = next.departure as i64 - previous.arrival as i64 - transfer.duration as i64;
+        transfers.push((i, previous.trip_id, previous.to_stop.stop_id, slack));
+    }
+
+    // Without transfers there is nothing to miss
+    if journey.transfers() == 0 {
+        journey.reliability = Some(1.0);
+        return Ok(());
+    }
+    let mut reliability = (!transfers.is_empty()).then_some(1.0);
+
+    // Load the delays for all transfers at once
+    let stop_ids: Vec<u32> = transfers.iter().map(|&(_, _, stop_id, _)| stop_id).collect();
+    let trip_ids: Vec<u32> = transfers.iter().map(|&(_, trip_id, _, _)| trip_id).collect();
+    let delays = get_arrival_delays(&stop_ids, db).await?;
+    let trips = get_trip_infos(&trip_ids, db).await?;
+
+    for (i, trip_id, stop_id, slack) in transfers {
+        let route_id = trips.get(&trip_id).map(|trip| trip.route_id);
+        let probability = delay_distribution(trip_id, route_id, stop_id, &delays)
+            .and_then(|delays| transfer_probability(&delays, slack));
+        let Some(probability) = probability else {
+            reliability = None;
+            continue;
+        };
+        reliability = reliability.map(|r| r * probability);
+        if let JourneyPart::Transfer(transfer) = &mut journey.parts[i] {
+            transfer.risk = RiskLevel::from_probability(probability);
+        }
+    }
+
+    journey.reliability = reliability;
+    Ok(())
+}
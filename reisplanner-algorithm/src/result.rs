@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use reisplanner_gtfs::gtfs::types::RouteType;
+
+use crate::database::queries::{get_stops, get_trip_infos, parse_stop_id, StopRow, TripInfo};
+use crate::reliability::RiskLevel;
+use crate::types::{Journey, JourneyPart};
+
+/// A journey with all information needed to display it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JourneyResult {
+    /// Departure time of the first vehicle in seconds since midnight
+    pub departure: Option<u32>,
+    /// Arrival time of the last vehicle in seconds since midnight
+    pub arrival: Option<u32>,
+    pub transfers: usize,
+    /// Probability that all transfers will be made
+    pub reliability: Option<f64>,
+    pub legs: Vec<Leg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Leg {
+    Vehicle(VehicleLeg),
+    Transfer(TransferStep),
+    /// A via stop where the journey is interrupted
+    Via(StopInfo),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopInfo {
+    pub stop_id: u32,
+    /// Id of the parent station (without the `stoparea:` prefix), or `stop_id` if there is none
+    pub parent_id: u32,
+    pub name: String,
+    pub platform_code: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VehicleLeg {
+    pub from: StopInfo,
+    pub to: StopInfo,
+    /// Times in seconds since midnight
+    pub departure: u32,
+    pub arrival: u32,
+    pub trip_id: u32,
+    pub trip_short_name: Option<String>,
+    pub headsign: String,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_long_name: String,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>,
+    pub wheelchair_accessible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferStep {
+    pub from: StopInfo,
+    pub to: StopInfo,
+    /// Duration in seconds
+    pub duration: u32,
+    pub wheelchair_accessible: bool,
+    pub risk: RiskLevel,
+}
+
+/// Convert journeys to results, looking up all stops and trips at once
+pub async fn journey_results(journeys: &[Journey], db: &RBatis) -> anyhow::Result<Vec<JourneyResult>> {
+    let mut stop_ids = HashSet::new();
+    let mut trip_ids = HashSet::new();
+    for part in journeys.iter().flat_map(|j| &j.parts) {
+        match part {
+            JourneyPart::Station(location) => {
+                stop_ids.insert(location.parent_id);
+            }
+            JourneyPart::Vehicle(leg) => {
+                stop_ids.extend([leg.from_stop.stop_id, leg.to_stop.stop_id]);
+                trip_ids.insert(leg.trip_id);
+            }
+            JourneyPart::Transfer(transfer) => {
+                stop_ids.extend([transfer.from_stop, transfer.to_stop]);
+            }
+        }
+    }
+
+    let stops = get_stops(&stop_ids.into_iter().collect::<Vec<_>>(), db).await?;
+    let trips = get_trip_infos(&trip_ids.into_iter().collect::<Vec<_>>(), db).await?;
+
+    journeys.iter()
+        .map(|journey| to_result(journey, &stops, &trips))
+        .collect()
+}
+
+pub async fn journey_result(journey: &Journey, db: &RBatis) -> anyhow::Result<JourneyResult> {
+    let mut results = journey_results(std::slice::from_ref(journey), db).await?;
+    Ok(results.remove(0))
+}
+
+fn to_result(
+    journey: &Journey,
+    stops: &HashMap<u32, StopRow>,
+    trips: &HashMap<u32, TripInfo>,
+) -> anyhow::Result<JourneyResult> {
+    let stop = |stop_id: u32| -> anyhow::Result<StopInfo> {
+        let row = stops.get(&stop_id)
+            .ok_or(anyhow::anyhow!("Stop {stop_id} not found"))?;
+        let parent_id = match &row.parent_station {
+            Some(parent) => parse_stop_id(parent)?,
+            None => stop_id,
+        };
+        Ok(StopInfo {
+            stop_id,
+            parent_id,
+            name: row.stop_name.clone(),
+            platform_code: row.platform_code.clone(),
+            lat: row.stop_lat,
+            lon: row.stop_lon,
+        })
+    };
+
+    let legs = journey.parts.iter()
+        .map(|part| Ok(match part {
+            JourneyPart::Station(location) => Leg::Via(stop(location.parent_id)?),
+            JourneyPart::Vehicle(leg) => {
+                let trip = trips.get(&leg.trip_id)
+                    .ok_or(anyhow::anyhow!("Trip {} not found", leg.trip_id))?;
+                Leg::Vehicle(VehicleLeg {
+                    from: stop(leg.from_stop.stop_id)?,
+                    to: stop(leg.to_stop.stop_id)?,
+                    departure: leg.departure,
+                    arrival: leg.arrival,
+                    trip_id: leg.trip_id,
+                    trip_short_name: trip.trip_short_name.clone(),
+                    headsign: trip.trip_headsign.clone(),
+                    route_id: trip.route_id,
+                    route_short_name: trip.route_short_name.clone(),
+                    route_long_name: trip.route_long_name.clone(),
+                    route_type: trip.route_type,
+                    agency_id: trip.agency_id.clone(),
+                    route_color: trip.route_color.clone(),
+                    route_text_color: trip.route_text_color.clone(),
+                    wheelchair_accessible: leg.wheelchair.is_accessible(),
+                })
+            }
+            JourneyPart::Transfer(transfer) => Leg::Transfer(TransferStep {
+                from: stop(transfer.from_stop)?,
+                to: stop(transfer.to_stop)?,
+                duration: transfer.duration,
+                wheelchair_accessible: transfer.wheelchair_accessible,
+                risk: transfer.risk,
+            }),
+        }))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(JourneyResult {
+        departure: journey.departure(),
+        arrival: journey.arrival(),
+        transfers: journey.transfers(),
+        reliability: journey.reliability,
+        legs,
+    })
+}
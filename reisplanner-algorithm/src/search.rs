@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use rbatis::RBatis;
+use serde::Serialize;
+use strsim::levenshtein;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::database::queries::{get_departure_counts, get_search_names, get_stop_locations, parse_stop_id};
+
+// Scores of the kinds of matches, the number of departures only orders matches of the same kind
+const EXACT: f64 = 1000.0;
+const PREFIX: f64 = 800.0;
+const WORD_PREFIX: f64 = 600.0;
+const CONTAINS: f64 = 400.0;
+const FUZZY: f64 = 200.0;
+const PER_TYPO: f64 = 50.0;
+const IMPORTANCE: f64 = 10.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    /// Station or stop without a parent station
+    pub stop_id: u32,
+    pub name: String,
+    /// The name or code that matched the query
+    pub matched: String,
+    pub departures: u32,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+struct Entry {
+    stop_id: u32,
+    name: String,
+    normalized: String,
+}
+
+struct IndexedStop {
+    name: String,
+    lat: f64,
+    lon: f64,
+    departures: u32,
+}
+
+/// In-memory index to search stations and stops by (part of) their names and codes.
+/// Supports prefix matching for autocomplete, small typos and ignores accents and punctuation,
+/// e.g. "s hertogenbosch" finds "’s-Hertogenbosch".
+pub struct SearchIndex {
+    entries: Vec<Entry>,
+    stops: HashMap<u32, IndexedStop>,
+}
+
+impl SearchIndex {
+    pub async fn load(db: &RBatis) -> anyhow::Result<Self> {
+        let departures = get_departure_counts(db).await?;
+        let stops = get_stop_locations(db).await?
+            .into_iter()
+            .map(|(stop_id, stop)| (stop_id, IndexedStop {
+                name: stop.stop_name,
+                lat: stop.stop_lat,
+                lon: stop.stop_lon,
+                departures: departures.get(&stop_id).copied().unwrap_or_default(),
+            }))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for search_name in get_search_names(db).await? {
+            let stop_id = parse_stop_id(&search_name.stop_id)?;
+            let normalized = normalize(&search_name.name);
+            if !normalized.is_empty() && seen.insert((stop_id, normalized.clone())) {
+                entries.push(Entry { stop_id, name: search_name.name, normalized });
+            }
+        }
+
+        Ok(Self { entries, stops })
+    }
+
+    /// Look up a station or stop by id
+    pub fn get(&self, stop_id: u32) -> Option<SearchResult> {
+        self.stops.get(&stop_id).map(|stop| SearchResult {
+            stop_id,
+            name: stop.name.clone(),
+            matched: stop_id.to_string(),
+            departures: stop.departures,
+            lat: stop.lat,
+            lon: stop.lon,
+        })
+    }
+
+    /// Find the best `limit` stations and stops for `query`, with one result per station
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_words: Vec<&str> = query.split(' ').collect();
+
+        let mut best: HashMap<u32, (f64, &Entry)> = HashMap::new();
+        for entry in &self.entries {
+            let Some(score) = match_score(&query, &query_words, &entry.normalized) else { continue };
+            let Some(stop) = self.stops.get(&entry.stop_id) else { continue };
+            let score = score + IMPORTANCE * (stop.departures as f64).ln_1p();
+            match best.get(&entry.stop_id) {
+                Some(&(current, _)) if current >= score => {}
+                _ => {
+                    best.insert(entry.stop_id, (score, entry));
+                }
+            }
+        }
+
+        let mut results: Vec<(f64, SearchResult)> = best.into_iter()
+            .map(|(stop_id, (score, entry))| {
+                let stop = &self.stops[&stop_id];
+                (score, SearchResult {
+                    stop_id,
+                    name: stop.name.clone(),
+                    matched: entry.name.clone(),
+                    departures: stop.departures,
+                    lat: stop.lat,
+                    lon: stop.lon,
+                })
+            })
+            .collect();
+        results.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+        results.into_iter().take(limit).map(|(_, result)| result).collect()
+    }
+}
+
+/// Lowercase, remove accents and replace punctuation by spaces
+pub fn normalize(text: &str) -> String {
+    let folded: String = text.nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn match_score(query: &str, query_words: &[&str], name: &str) -> Option<f64> {
+    if name == query {
+        return Some(EXACT);
+    }
+    if name.starts_with(query) {
+        return Some(PREFIX);
+    }
+    if name.contains(&format!(" {query}")) {
+        return Some(WORD_PREFIX);
+    }
+    if name.contains(query) {
+        return Some(CONTAINS);
+    }
+
+    // Every word of the query should be (the start of) a word in the name, with few typos
+    let words: Vec<&str> = name.split(' ').collect();
+    let mut typos = 0;
+    for query_word in query_words {
+        typos += words.iter()
+            .filter_map(|word| word_typos(query_word, word))
+            .min()?;
+    }
+    Some(FUZZY - PER_TYPO * typos as f64)
+}
+
+/// The number of typos between `query_word` and (the start of) `word`,
+/// or None if there are too many for the length of the query
+fn word_typos(query_word: &str, word: &str) -> Option<usize> {
+    let allowed = match query_word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let prefix: String = word.chars().take(query_word.chars().count()).collect();
+    let typos = levenshtein(query_word, word).min(levenshtein(query_word, &prefix));
+    (typos <= allowed).then_some(typos)
+}
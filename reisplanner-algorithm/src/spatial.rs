@@ -0,0 +1,185 @@
+use std::f64::consts::PI;
+
+use rbatis::RBatis;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use serde::Serialize;
+
+use crate::database::queries::{get_parent_station_map, get_stop_locations};
+
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A stop in a `StopIndex`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpatialStop {
+    pub stop_id: u32,
+    /// Parent station, or the stop itself if it has none
+    pub parent_id: u32,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NearbyStop {
+    #[serde(flatten)]
+    pub stop: SpatialStop,
+    /// Great-circle distance in meters
+    pub distance: f64,
+}
+
+/// Stops are stored as points on a sphere (in meters), so straight-line distances
+/// order the same as great-circle distances
+#[derive(Debug, Clone)]
+struct IndexedStop {
+    stop: SpatialStop,
+    point: [f64; 3],
+}
+
+impl RTreeObject for IndexedStop {
+    type Envelope = AABB<[f64; 3]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point(self.point)
+    }
+}
+
+impl PointDistance for IndexedStop {
+    fn distance_2(&self, point: &[f64; 3]) -> f64 {
+        self.point.distance_2(point)
+    }
+}
+
+/// In-memory R-tree over stop coordinates, for "stops within a radius",
+/// "nearest stops" and bounding box queries
+pub struct StopIndex {
+    tree: RTree<IndexedStop>,
+}
+
+impl StopIndex {
+    pub fn new(stops: impl IntoIterator<Item = SpatialStop>) -> Self {
+        let stops = stops.into_iter()
+            .map(|stop| IndexedStop { point: to_point(stop.lat, stop.lon), stop })
+            .collect();
+        Self { tree: RTree::bulk_load(stops) }
+    }
+
+    /// Index all stops, including platforms
+    pub async fn load(db: &RBatis) -> anyhow::Result<Self> {
+        Self::load_filtered(db, |_| true).await
+    }
+
+    /// Index stations and stops without a parent station
+    pub async fn load_stations(db: &RBatis) -> anyhow::Result<Self> {
+        Self::load_filtered(db, |stop| stop.stop_id == stop.parent_id).await
+    }
+
+    async fn load_filtered(db: &RBatis, filter: impl Fn(&SpatialStop) -> bool) -> anyhow::Result<Self> {
+        let parents = get_parent_station_map(db).await?;
+        let stops = get_stop_locations(db).await?
+            .into_iter()
+            .map(|(stop_id, stop)| SpatialStop {
+                stop_id,
+                parent_id: parents.get(&stop_id).copied().unwrap_or(stop_id),
+                lat: stop.stop_lat,
+                lon: stop.stop_lon,
+            })
+            .filter(filter);
+        Ok(Self::new(stops))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.size() == 0
+    }
+
+    /// Stops within `radius` meters, nearest first
+    pub fn within_radius(&self, lat: f64, lon: f64, radius: f64) -> Vec<NearbyStop> {
+        let point = to_point(lat, lon);
+        let chord = 2.0 * EARTH_RADIUS * (radius.min(PI * EARTH_RADIUS) / (2.0 * EARTH_RADIUS)).sin();
+        let mut stops: Vec<NearbyStop> = self.tree
+            .locate_within_distance(point, chord * chord)
+            .map(|stop| nearby(stop, &point))
+            .collect();
+        stops.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        stops
+    }
+
+    /// The `k` nearest stops, nearest first
+    pub fn nearest(&self, lat: f64, lon: f64, k: usize) -> Vec<NearbyStop> {
+        let point = to_point(lat, lon);
+        self.tree.nearest_neighbor_iter(&point)
+            .take(k)
+            .map(|stop| nearby(stop, &point))
+            .collect()
+    }
+
+    /// Stops within a bounding box, in no particular order.
+    /// The box may not cross the antimeridian.
+    pub fn in_bbox(&self, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Vec<SpatialStop> {
+        self.tree
+            .locate_in_envelope_intersecting(&bbox_envelope(min_lat, min_lon, max_lat, max_lon))
+            .filter(|s| (min_lat..=max_lat).contains(&s.stop.lat) && (min_lon..=max_lon).contains(&s.stop.lon))
+            .map(|s| s.stop.clone())
+            .collect()
+    }
+}
+
+fn nearby(stop: &IndexedStop, point: &[f64; 3]) -> NearbyStop {
+    let chord = stop.point.distance_2(point).sqrt();
+    NearbyStop {
+        stop: stop.stop.clone(),
+        distance: 2.0 * EARTH_RADIUS * (chord / (2.0 * EARTH_RADIUS)).min(1.0).asin(),
+    }
+}
+
+fn to_point(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [
+        EARTH_RADIUS * lat.cos() * lon.cos(),
+        EARTH_RADIUS * lat.cos() * lon.sin(),
+        EARTH_RADIUS * lat.sin(),
+    ]
+}
+
+/// An envelope containing all points on the sphere within a bounding box
+fn bbox_envelope(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> AABB<[f64; 3]> {
+    let (min_lat, max_lat) = (min_lat.to_radians(), max_lat.to_radians());
+    let (min_lon, max_lon) = (min_lon.to_radians(), max_lon.to_radians());
+    let contains = |from: f64, to: f64, angle: f64| {
+        [angle - 2.0 * PI, angle, angle + 2.0 * PI].iter().any(|a| (from..=to).contains(a))
+    };
+    // Range of a function over an interval, given the angles where it has an extreme
+    let range = |f: fn(f64) -> f64, from: f64, to: f64, maximum: f64, minimum: f64| {
+        let (mut low, mut high) = (f(from).min(f(to)), f(from).max(f(to)));
+        if contains(from, to, maximum) {
+            high = 1.0;
+        }
+        if contains(from, to, minimum) {
+            low = -1.0;
+        }
+        (low, high)
+    };
+
+    let (cos_lat_low, cos_lat_high) = range(f64::cos, min_lat, max_lat, 0.0, PI);
+    let (cos_lon_low, cos_lon_high) = range(f64::cos, min_lon, max_lon, 0.0, PI);
+    let (sin_lon_low, sin_lon_high) = range(f64::sin, min_lon, max_lon, PI / 2.0, -PI / 2.0);
+    // Products of two ranges, cos(lat) is never negative
+    let product = |low: f64, high: f64| {
+        let values = [cos_lat_low * low, cos_lat_low * high, cos_lat_high * low, cos_lat_high * high];
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (min * EARTH_RADIUS, max * EARTH_RADIUS)
+    };
+    let (min_x, max_x) = product(cos_lon_low, cos_lon_high);
+    let (min_y, max_y) = product(sin_lon_low, sin_lon_high);
+    let (min_z, max_z) = (min_lat.sin() * EARTH_RADIUS, max_lat.sin() * EARTH_RADIUS);
+
+    // Against rounding errors for stops on the edge
+    let padding = 1.0;
+    AABB::from_corners(
+        [min_x - padding, min_y - padding, min_z - padding],
+        [max_x + padding, max_y + padding, max_z + padding],
+    )
+}
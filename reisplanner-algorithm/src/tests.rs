@@ -1,10 +1,43 @@
+use std::collections::{HashMap, HashSet};
+use std::{env, fs, io};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, TimeZone};
+use geo::{Distance, Haversine, Point};
+use rbatis::RBatis;
 use tracing_subscriber::EnvFilter;
 
-use reisplanner_gtfs::utils::TimeTuple;
+use reisplanner_gtfs::gtfs::types::{AllowedType, DelayRecord, RouteType};
+use reisplanner_gtfs::utils::{naive_date_to_date, TimeTuple};
+use reisplanner_gtfs::shapes::{update_shape_polylines, ShapeSettings};
 use crate::algorithms::csa;
 use crate::algorithms::raptor;
+use crate::algorithms::raptor::{Connection, Location, QueryOptions, RRoute, TripAttributes, ViaStop};
+use crate::algorithms::raptor::options::{BicycleOptions, WHEELCHAIR_EXTRA_TRANSFER_TIME};
+use crate::algorithms::raptor::alternatives::{rank_journeys, AlternativesOptions};
+use crate::algorithms::raptor::matrix::{median_travel_time, run_travel_time_matrix, MatrixOptions};
+use crate::algorithms::raptor::isochrone::{buffer_stops, isochrone_to_geojson, run_isochrone, BufferOptions, IsochroneStop};
+use crate::algorithms::raptor::{Accessibility, TransferLeg, TripLeg};
+use crate::types::{Journey, JourneyPart};
+use crate::reliability::{annotate_reliability, transfer_probability, RiskLevel};
+use crate::board::{get_arrivals, get_departures};
 use crate::database::new_db_connection;
+use crate::database::queries::{get_active_services, get_stop_arrivals, get_stop_departures, get_wheelchair_stops, StopVisit};
+use crate::result::{journey_result, JourneyResult, Leg, StopInfo, VehicleLeg};
+use crate::trip_details::{get_trip_details, get_trip_details_by_number, last_passed_stop};
 use crate::getters::get_stop;
+use crate::fixture;
+use crate::utils::{deserialize_from_disk, serialize_to_disk};
+use crate::export::{feed_seconds, today, TIMEZONE};
+use crate::export::geojson::journey_to_geojson;
+use crate::export::gpx::journey_to_gpx;
+use crate::export::ical::journey_to_ical;
+use crate::export::kml::journey_to_kml;
+use crate::geometry::leg_polyline;
+use crate::search::{normalize, SearchIndex};
+use crate::spatial::StopIndex;
+
 
 #[tokio::test]
 async fn csa_algorithm() -> anyhow::Result<()>{
@@ -40,6 +73,7 @@ async fn csa_algorithm() -> anyhow::Result<()>{
 }
 
 #[tokio::test]
+#[allow(clippy::assertions_on_constants)]
 async fn raptor_algorithm() -> anyhow::Result<()> {
     let log_level = EnvFilter::try_from_default_env()
         // .unwrap_or(EnvFilter::new("error,reisplanner=debug,rbatis=debug"));
@@ -65,7 +99,8 @@ async fn raptor_algorithm() -> anyhow::Result<()> {
         println!("Planning route between {dep_name} and {arr_name}");
 
         let result = raptor::run_raptor(
-            departure, arrival, departure_time, &timetable, &transfers, db
+            departure, arrival, departure_time, &timetable, &transfers,
+            &QueryOptions::default(), db
         ).await;
         // TODO proper test cases instead of assert!(false)
         match result {
@@ -85,4 +120,846 @@ async fn raptor_algorithm() -> anyhow::Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn timetable_cache_layout() -> anyhow::Result<()> {
+    let path = env::temp_dir().join("reisplanner_timetable_layout.blob");
+    let path = path.to_str().expect("Temporary path is valid UTF-8");
+    // Routes as cached before they had trip attributes, a type and an operator
+    let location = Location { stop_id: fixture::UTRECHT_PLATFORM_5, parent_id: fixture::UTRECHT };
+    let old: HashMap<u32, (Vec<Location>, Vec<Vec<Connection>>)> = HashMap::from([(0, (vec![location], Vec::new()))]);
+    serialize_to_disk(&old, path)?;
+
+    // An error instead of a panic, so that the timetable is regenerated
+    let error = deserialize_from_disk::<HashMap<u32, RRoute>>(path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn raptor_route_filters() {
+    let train = RRoute::new(Vec::new(), RouteType::Train, "NS".to_string());
+    let bus = RRoute::new(Vec::new(), RouteType::Bus, "QBUZZ".to_string());
+
+    let options = QueryOptions::default();
+    assert!(options.allows_route(&train));
+    assert!(options.allows_route(&bus));
+
+    let options = QueryOptions::train_only();
+    assert!(options.allows_route(&train));
+    assert!(!options.allows_route(&bus));
+
+    let options = QueryOptions {
+        excluded_agencies: ["NS".to_string()].into(),
+        ..Default::default()
+    };
+    assert!(!options.allows_route(&train));
+    assert!(options.allows_route(&bus));
+}
+
+/// The fixture database of `date` with its timetable and transfer times
+async fn fixture_timetable(date: NaiveDate) -> anyhow::Result<(RBatis, HashMap<u32, RRoute>, HashMap<u32, u32>)> {
+    let db = fixture::fixture_db(date).await?;
+    let timetable = raptor::get_timetable(&db, false).await?;
+    let transfers = raptor::generate_transfer_times(&db).await?;
+    Ok((db, timetable, transfers))
+}
+
+/// Plan a journey on the fixture network
+async fn fixture_raptor(
+    departure_stop: u32,
+    arrival_stop: u32,
+    departure_time: TimeTuple,
+    options: &QueryOptions,
+) -> anyhow::Result<Option<Journey>> {
+    let (db, timetable, transfers) = fixture_timetable(today()).await?;
+    let result = raptor::run_raptor(
+        departure_stop, arrival_stop, departure_time, &timetable, &transfers, options, &db,
+    ).await?;
+    Ok(result.map(Journey::from))
+}
+
+/// The trips and arrival time of a journey
+fn trips_and_arrival(journey: &Journey) -> (Vec<u32>, u32) {
+    (journey.legs().map(|leg| leg.trip_id).collect(), journey.arrival().unwrap_or_default())
+}
+
+#[tokio::test]
+async fn raptor_skips_excluded_routes() -> anyhow::Result<()> {
+    let (from, to, time) = (fixture::DEN_HAAG, fixture::LIEGE, TimeTuple(10, 0, 0));
+
+    let result = fixture_raptor(from, to, time, &QueryOptions::default()).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![104, 106], TimeTuple(13, 10, 0).into())));
+
+    // Without NMBS only the bus is left
+    let options = QueryOptions {
+        excluded_agencies: ["NMBS".to_string()].into(),
+        ..Default::default()
+    };
+    let result = fixture_raptor(from, to, time, &options).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![104, 107], TimeTuple(14, 30, 0).into())));
+    let options = QueryOptions {
+        excluded_route_types: [RouteType::Bus].into(),
+        ..options
+    };
+    assert!(fixture_raptor(from, to, time, &options).await?.is_none());
+    let options = QueryOptions {
+        allowed_agencies: Some(["NS".to_string()].into()),
+        ..Default::default()
+    };
+    assert!(fixture_raptor(from, to, time, &options).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn raptor_wheelchair() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let stops = get_wheelchair_stops(&db).await?;
+    // Platforms inherit from their station, unless they are set themselves
+    assert!(stops.contains(&fixture::UTRECHT_PLATFORM_5) && stops.contains(&4001));
+    assert!(!stops.contains(&fixture::UTRECHT_PLATFORM_7));
+    assert!(!stops.contains(&2001));
+
+    // Trip 106 is not accessible, so we continue with trip 105
+    let options = QueryOptions { wheelchair: true, ..Default::default() };
+    let journey = fixture_raptor(fixture::DEN_HAAG, fixture::LIEGE, TimeTuple(10, 0, 0), &options).await?
+        .expect("Liège is reachable by wheelchair");
+    assert_eq!(trips_and_arrival(&journey), (vec![104, 105], TimeTuple(13, 40, 0).into()));
+    let Some(JourneyPart::Transfer(transfer)) = journey.parts.get(1) else { panic!("Expected a transfer") };
+    // 5 minutes at 's-Hertogenbosch and 5 extra for wheelchair users
+    assert_eq!(transfer.duration, 300 + WHEELCHAIR_EXTRA_TRANSFER_TIME);
+    assert!(transfer.wheelchair_accessible);
+    let Some(JourneyPart::Vehicle(leg)) = journey.parts.get(2) else { panic!("Expected a vehicle leg") };
+    assert_eq!(leg.wheelchair, Accessibility { trip: true, boarding: true, alighting: true });
+
+    // Amsterdam Centraal is not accessible
+    assert!(fixture_raptor(fixture::DEN_HAAG, fixture::AMSTERDAM, TimeTuple(9, 0, 0), &options).await?.is_none());
+    Ok(())
+}
+
+#[test]
+fn raptor_bicycle_options() {
+    let trip = |bikes_allowed| TripAttributes {
+        trip_id: 1,
+        service_id: 1,
+        wheelchair_accessible: AllowedType::NoInformation,
+        bikes_allowed,
+    };
+    let connection = |departure: TimeTuple, arrival: TimeTuple| Connection {
+        departure_station: Location { stop_id: 1, parent_id: 1 },
+        arrival_station: Location { stop_id: 2, parent_id: 2 },
+        departure: departure.into(),
+        arrival: arrival.into(),
+        trip_id: 1,
+    };
+
+    let options = QueryOptions {
+        bicycle: Some(BicycleOptions::default()),
+        ..Default::default()
+    };
+    assert!(options.allows_trip(&trip(AllowedType::Allowed)));
+    assert!(!options.allows_trip(&trip(AllowedType::NoInformation)));
+    assert!(!options.allows_trip(&trip(AllowedType::NotAllowed)));
+    assert!(options.allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+
+    // Rush hours on a Friday, but not after midnight on Saturday
+    let friday = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+    let options = QueryOptions {
+        bicycle: Some(BicycleOptions::ns_rush_hours(friday)),
+        ..Default::default()
+    };
+    assert!(options.allows_connection(&connection(TimeTuple(10, 0, 0), TimeTuple(10, 30, 0))));
+    assert!(!options.allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+    assert!(options.allows_connection(&connection(TimeTuple(9, 0, 0), TimeTuple(9, 10, 0))));
+    assert!(!options.allows_connection(&connection(TimeTuple(16, 0, 0), TimeTuple(16, 20, 0))));
+    assert!(!options.allows_connection(&connection(TimeTuple(18, 10, 0), TimeTuple(18, 40, 0))));
+    assert!(options.allows_connection(&connection(TimeTuple(18, 30, 0), TimeTuple(18, 40, 0))));
+    assert!(options.allows_connection(&connection(TimeTuple(30, 40, 0), TimeTuple(31, 0, 0))));
+    // The night from Thursday to Friday
+    let options = QueryOptions {
+        bicycle: Some(BicycleOptions::ns_rush_hours(friday.pred_opt().unwrap())),
+        ..Default::default()
+    };
+    assert!(!options.allows_connection(&connection(TimeTuple(30, 40, 0), TimeTuple(31, 0, 0))));
+    let options = QueryOptions {
+        bicycle: Some(BicycleOptions::ns_rush_hours(friday.succ_opt().unwrap())),
+        ..Default::default()
+    };
+    assert!(options.allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+
+    // No rush hours in July and August, but on the morning of the 1st of September
+    let summer = |date| QueryOptions {
+        bicycle: Some(BicycleOptions::ns_rush_hours(date)),
+        ..Default::default()
+    };
+    let wednesday = NaiveDate::from_ymd_opt(2026, 7, 15).unwrap();
+    assert!(summer(wednesday).allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+    assert!(summer(wednesday).allows_connection(&connection(TimeTuple(17, 0, 0), TimeTuple(17, 30, 0))));
+    let monday = NaiveDate::from_ymd_opt(2026, 8, 31).unwrap();
+    assert!(summer(monday).allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+    assert!(!summer(monday).allows_connection(&connection(TimeTuple(30, 40, 0), TimeTuple(31, 0, 0))));
+
+    let options = QueryOptions {
+        bicycle: Some(BicycleOptions {
+            include_no_information: true,
+            restricted_windows: Vec::new(),
+        }),
+        ..Default::default()
+    };
+    assert!(options.allows_trip(&trip(AllowedType::NoInformation)));
+    assert!(options.allows_connection(&connection(TimeTuple(8, 50, 0), TimeTuple(9, 10, 0))));
+}
+
+#[tokio::test]
+async fn raptor_service_dates() -> anyhow::Result<()> {
+    let today = today();
+    let db = fixture::fixture_db(today).await?;
+    assert_eq!(get_active_services(today, &db).await?, HashSet::from([1]));
+    let tomorrow = today.succ_opt().unwrap();
+    assert!(get_active_services(tomorrow, &db).await?.is_empty());
+
+    // Trips only run on the dates of their service
+    let on_date = |services| QueryOptions { services: Some(services), ..Default::default() };
+    let options = on_date(get_active_services(today, &db).await?);
+    let result = fixture_raptor(fixture::DEN_HAAG, fixture::AMSTERDAM, TimeTuple(9, 0, 0), &options).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![100], TimeTuple(10, 27, 0).into())));
+    let options = on_date(get_active_services(tomorrow, &db).await?);
+    assert!(fixture_raptor(fixture::DEN_HAAG, fixture::AMSTERDAM, TimeTuple(9, 0, 0), &options).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn raptor_boards_earliest_trip() -> anyhow::Result<()> {
+    // Trip 106 departs before trip 105 of the same route, but comes after it in the stop times
+    let result = fixture_raptor(fixture::DEN_BOSCH, fixture::LIEGE, TimeTuple(12, 0, 0), &QueryOptions::default()).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![106], TimeTuple(13, 10, 0).into())));
+
+    // Boarding trip 100 at its second stop
+    let result = fixture_raptor(fixture::UTRECHT, fixture::AMSTERDAM, TimeTuple(10, 0, 0), &QueryOptions::default()).await?;
+    let journey = result.expect("Amsterdam is reachable from Utrecht");
+    assert_eq!(trips_and_arrival(&journey), (vec![100], TimeTuple(10, 27, 0).into()));
+    assert_eq!(journey.departure(), Some(TimeTuple(10, 2, 0).into()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn raptor_via_and_avoided_stops() -> anyhow::Result<()> {
+    let (db, timetable, transfers) = fixture_timetable(today()).await?;
+    let options = QueryOptions::default();
+    let via_den_bosch = |min_stay| [ViaStop { stop_id: fixture::DEN_BOSCH, min_stay }];
+
+    // Staying half an hour in 's-Hertogenbosch misses trip 106
+    let journey = raptor::run_raptor_via(
+        fixture::DEN_HAAG, fixture::LIEGE, &via_den_bosch(1800), TimeTuple(10, 0, 0),
+        &timetable, &transfers, &options, &db,
+    ).await?.map(Journey::from).expect("Liège is reachable via 's-Hertogenbosch");
+    assert_eq!(trips_and_arrival(&journey), (vec![104, 105], TimeTuple(13, 40, 0).into()));
+    assert_eq!(journey.transfers(), 1);
+    assert!(matches!(journey.parts[1], JourneyPart::Station(Location { parent_id: fixture::DEN_BOSCH, .. })));
+    let JourneyPart::Transfer(transfer) = &journey.parts[2] else { panic!("Expected a transfer") };
+    assert_eq!((transfer.from_stop, transfer.to_stop, transfer.duration), (4001, 4001, 1800));
+
+    // Without a minimum stay, the transfer time of the station is used
+    let journey = raptor::run_raptor_via(
+        fixture::DEN_HAAG, fixture::LIEGE, &via_den_bosch(0), TimeTuple(10, 0, 0),
+        &timetable, &transfers, &options, &db,
+    ).await?.map(Journey::from).expect("Liège is reachable via 's-Hertogenbosch");
+    assert_eq!(trips_and_arrival(&journey), (vec![104, 106], TimeTuple(13, 10, 0).into()));
+    let JourneyPart::Transfer(transfer) = &journey.parts[2] else { panic!("Expected a transfer") };
+    assert_eq!(transfer.duration, 300);
+
+    // Trip 100 may not pass through Utrecht
+    let options = QueryOptions { avoided_stops: [fixture::UTRECHT].into(), ..Default::default() };
+    assert!(fixture_raptor(fixture::DEN_HAAG, fixture::AMSTERDAM, TimeTuple(9, 0, 0), &options).await?.is_none());
+    let result = fixture_raptor(fixture::DEN_HAAG, fixture::LIEGE, TimeTuple(9, 0, 0), &options).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![104, 106], TimeTuple(13, 10, 0).into())));
+    let options = QueryOptions { avoided_stops: [fixture::DEN_BOSCH].into(), ..Default::default() };
+    assert!(fixture_raptor(fixture::DEN_HAAG, fixture::LIEGE, TimeTuple(9, 0, 0), &options).await?.is_none());
+    Ok(())
+}
+
+#[test]
+fn raptor_transfer_options() {
+    let options = QueryOptions::default();
+    assert_eq!(options.transfer_duration(300, None), 300);
+
+    let options = QueryOptions {
+        transfer_buffer: 120,
+        min_transfer_times: [((RouteType::Train, RouteType::Bus), 600)].into(),
+        max_transfer_time: Some(900),
+        ..Default::default()
+    };
+    assert_eq!(options.transfer_duration(300, None), 420);
+    assert_eq!(options.transfer_duration(300, Some((RouteType::Train, RouteType::Train))), 420);
+    assert_eq!(options.transfer_duration(300, Some((RouteType::Train, RouteType::Bus))), 720);
+    assert!(options.allows_transfer_time(900));
+    assert!(!options.allows_transfer_time(901));
+}
+
+#[tokio::test]
+async fn raptor_transfers() -> anyhow::Result<()> {
+    let (from, to, time) = (fixture::DEN_HAAG, fixture::LIEGE, TimeTuple(10, 0, 0));
+    let journey = fixture_raptor(from, to, time, &QueryOptions::default()).await?
+        .expect("Liège is reachable from Den Haag");
+    assert_eq!(trips_and_arrival(&journey), (vec![104, 106], TimeTuple(13, 10, 0).into()));
+    let JourneyPart::Transfer(transfer) = &journey.parts[1] else { panic!("Expected a transfer") };
+    assert_eq!((transfer.from_stop, transfer.to_stop, transfer.duration), (4001, 4001, 300));
+
+    // With 10 extra minutes, the transfer to trip 106 at 12:10 is missed
+    let options = QueryOptions { transfer_buffer: 600, ..Default::default() };
+    let journey = fixture_raptor(from, to, time, &options).await?
+        .expect("Liège is reachable from Den Haag");
+    assert_eq!(trips_and_arrival(&journey), (vec![104, 105], TimeTuple(13, 40, 0).into()));
+    let JourneyPart::Transfer(transfer) = &journey.parts[1] else { panic!("Expected a transfer") };
+    assert_eq!(transfer.duration, 900);
+
+    // Transferring in 's-Hertogenbosch takes 5 minutes
+    let options = QueryOptions { max_transfer_time: Some(300), ..Default::default() };
+    let result = fixture_raptor(from, to, time, &options).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![104, 106], TimeTuple(13, 10, 0).into())));
+    let options = QueryOptions { max_transfer_time: Some(299), ..Default::default() };
+    assert!(fixture_raptor(from, to, time, &options).await?.is_none());
+    // A direct trip is still possible
+    let result = fixture_raptor(fixture::DEN_HAAG, fixture::AMSTERDAM, TimeTuple(9, 0, 0), &options).await?;
+    assert_eq!(result.as_ref().map(trips_and_arrival), Some((vec![100], TimeTuple(10, 27, 0).into())));
+    Ok(())
+}
+
+#[test]
+fn raptor_rank_alternatives() {
+    let leg = |trip_id, departure, arrival| JourneyPart::Vehicle(TripLeg {
+        from_stop: Location { stop_id: 1, parent_id: 1 },
+        to_stop: Location { stop_id: 2, parent_id: 2 },
+        departure,
+        arrival,
+        trip_id,
+        route_id: 0,
+        wheelchair: Accessibility::default(),
+    });
+
+    let early = Journey::from(vec![leg(1, 1000, 1600), leg(2, 4600, 5200)]);
+    let late = Journey::from(vec![leg(3, 3600, 4200), leg(2, 4600, 5200)]);
+    let direct = Journey::from(vec![leg(4, 3000, 5400)]);
+    let other_feeder = Journey::from(vec![leg(5, 3700, 4300), leg(2, 4600, 5200)]);
+    assert!(late.dominates(&early));
+    assert!(!late.dominates(&direct));
+
+    let ranked = rank_journeys(
+        vec![early, late, direct, other_feeder],
+        &AlternativesOptions::default(),
+    );
+    let departures: Vec<_> = ranked.iter().map(|j| j.departure()).collect();
+    assert_eq!(departures, [Some(3700), Some(3000)]);
+}
+
+#[test]
+fn transfer_reliability() {
+    let delays = [0, 0, 30, 60, 60, 120, 180, 240, 300, 600];
+    assert_eq!(transfer_probability(&delays, 60), Some(0.5));
+    assert_eq!(transfer_probability(&delays, 600), Some(1.0));
+    assert_eq!(transfer_probability(&[], 600), None);
+    assert_eq!(RiskLevel::from_probability(0.5), RiskLevel::High);
+    assert_eq!(RiskLevel::from_probability(0.9), RiskLevel::Medium);
+    assert_eq!(RiskLevel::from_probability(1.0), RiskLevel::Low);
+}
+
+#[tokio::test]
+async fn journey_reliability() -> anyhow::Result<()> {
+    let today = today();
+    let db = fixture::fixture_db(today).await?;
+    // Trip 104 arrived in 's-Hertogenbosch on time on 8 days, and 10 minutes late on 2 days
+    let records: Vec<DelayRecord> = (0..10u64)
+        .map(|days| DelayRecord {
+            service_date: naive_date_to_date(today - chrono::Days::new(days + 1)),
+            trip_id: 104,
+            stop_sequence: 2,
+            route_id: 10,
+            stop_id: 4001,
+            arrival_delay: Some(if days < 2 { 600 } else { 0 }),
+            ..Default::default()
+        })
+        .collect();
+    DelayRecord::insert_batch(&db, &records, records.len() as u64).await?;
+
+    let location = |stop_id, parent_id| Location { stop_id, parent_id };
+    let leg = |trip_id, from_stop, to_stop, departure: TimeTuple, arrival: TimeTuple| JourneyPart::Vehicle(TripLeg {
+        from_stop,
+        to_stop,
+        departure: departure.into(),
+        arrival: arrival.into(),
+        trip_id,
+        route_id: 0,
+        wheelchair: Accessibility::default(),
+    });
+    let transfer = |stop_id, duration| JourneyPart::Transfer(TransferLeg {
+        from_stop: stop_id,
+        to_stop: stop_id,
+        duration,
+        wheelchair_accessible: false,
+        risk: RiskLevel::Unknown,
+    });
+    let den_bosch = location(4001, fixture::DEN_BOSCH);
+    let to_den_bosch = leg(104, location(3001, fixture::DEN_HAAG), den_bosch, TimeTuple(11, 0, 0), TimeTuple(12, 0, 0));
+    let to_liege = leg(106, den_bosch, location(5001, fixture::LIEGE), TimeTuple(12, 10, 0), TimeTuple(13, 10, 0));
+
+    // 5 minutes of slack, so the transfer is missed on 2 of 10 days
+    let mut journey = Journey::from(vec![to_den_bosch.clone(), transfer(4001, 300), to_liege.clone()]);
+    annotate_reliability(&mut journey, &db).await?;
+    assert_eq!(journey.reliability, Some(0.8));
+    let JourneyPart::Transfer(first) = &journey.parts[1] else { panic!("Expected a transfer") };
+    assert_eq!(first.risk, RiskLevel::Medium);
+
+    // There are no delays of trip 100 in Utrecht, so the journey reliability is unknown
+    let utrecht = location(fixture::UTRECHT_PLATFORM_5, fixture::UTRECHT);
+    let mut journey = Journey::from(vec![
+        leg(100, location(3001, fixture::DEN_HAAG), utrecht, TimeTuple(9, 20, 0), TimeTuple(10, 0, 0)),
+        transfer(fixture::UTRECHT_PLATFORM_5, 300),
+        to_den_bosch,
+        transfer(4001, 300),
+        to_liege,
+    ]);
+    annotate_reliability(&mut journey, &db).await?;
+    assert_eq!(journey.reliability, None);
+    let JourneyPart::Transfer(first) = &journey.parts[1] else { panic!("Expected a transfer") };
+    assert_eq!(first.risk, RiskLevel::Unknown);
+    let JourneyPart::Transfer(second) = &journey.parts[3] else { panic!("Expected a transfer") };
+    assert_eq!(second.risk, RiskLevel::Medium);
+
+    let mut direct = Journey::from(vec![leg(100, location(3001, fixture::DEN_HAAG), utrecht, TimeTuple(9, 20, 0), TimeTuple(10, 0, 0))]);
+    annotate_reliability(&mut direct, &db).await?;
+    assert_eq!(direct.reliability, Some(1.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn isochrone_reachable_stops() -> anyhow::Result<()> {
+    let (db, timetable, transfers) = fixture_timetable(today()).await?;
+    let options = QueryOptions::default();
+    let reachable = |stops: Vec<IsochroneStop>| -> Vec<(u32, u32)> {
+        stops.iter().map(|s| (s.stop_id, s.travel_time)).collect()
+    };
+
+    // Until 10:30, Amsterdam is reached by trip 100 at 10:27
+    let stops = run_isochrone(fixture::DEN_HAAG, TimeTuple(9, 0, 0), 5400, &timetable, &transfers, &options, &db).await?;
+    assert_eq!(reachable(stops.clone()), [(fixture::DEN_HAAG, 0), (fixture::UTRECHT, 3600), (fixture::AMSTERDAM, 5220)]);
+    assert_eq!(stops[1].stop_name, "Utrecht Centraal");
+    assert_eq!(stops[1].arrival, u32::from(TimeTuple(10, 0, 0)));
+
+    let stops = run_isochrone(fixture::DEN_HAAG, TimeTuple(9, 0, 0), 3600, &timetable, &transfers, &options, &db).await?;
+    assert_eq!(reachable(stops), [(fixture::DEN_HAAG, 0), (fixture::UTRECHT, 3600)]);
+    // Arriving at 12:00 exactly, but there is no time left to reach Liège
+    let stops = run_isochrone(fixture::DEN_HAAG, TimeTuple(9, 0, 0), 3 * 3600, &timetable, &transfers, &options, &db).await?;
+    assert_eq!(stops.last().map(|s| (s.stop_id, s.travel_time)), Some((fixture::DEN_BOSCH, 3 * 3600)));
+    Ok(())
+}
+
+#[test]
+fn isochrone_geojson() {
+    let stop = |stop_id, lat, lon, travel_time| IsochroneStop {
+        stop_id, stop_name: String::new(), lat, lon, arrival: 36000 + travel_time, travel_time,
+    };
+    // Utrecht Centraal, Utrecht Vaartsche Rijn (1.3 km) and Amsterdam Centraal
+    let stops = [
+        stop(1, 52.0894, 5.1100, 0),
+        stop(2, 52.0799, 5.1232, 300),
+        stop(3, 52.3789, 4.9003, 1800),
+    ];
+    let buffer = BufferOptions::default();
+
+    // The circles around the stops in Utrecht overlap, the one in Amsterdam has no time left
+    let polygons = buffer_stops(&stops, 1800, &buffer);
+    assert_eq!(polygons.0.len(), 1);
+    let polygons = buffer_stops(&stops, 3600, &buffer);
+    assert_eq!(polygons.0.len(), 2);
+
+    let geojson = isochrone_to_geojson(&stops, 1800, None);
+    assert_eq!(geojson.features.len(), 3);
+    let geojson = isochrone_to_geojson(&stops, 1800, Some(&buffer));
+    assert_eq!(geojson.features.len(), 4);
+}
+
+#[test]
+fn travel_time_matrix_median() {
+    assert_eq!(median_travel_time(&[]), None);
+    assert_eq!(median_travel_time(&[Some(600), Some(1200), Some(900)]), Some(900));
+    assert_eq!(median_travel_time(&[Some(600), Some(1200), None, Some(900)]), Some(1050));
+    // Unreachable for half of the departures
+    assert_eq!(median_travel_time(&[Some(600), None]), None);
+
+    let matrix = MatrixOptions { window_start: 3600, window_end: 4200, step: 300 };
+    assert_eq!(matrix.departure_times().collect::<Vec<_>>(), [3600, 3900, 4200]);
+}
+
+#[tokio::test]
+async fn travel_time_matrix_on_fixture() -> anyhow::Result<()> {
+    let (db, timetable, transfers) = fixture_timetable(today()).await?;
+    let (timetable, transfers) = (Arc::new(timetable), Arc::new(transfers));
+    let matrix = MatrixOptions { window_start: TimeTuple(9, 0, 0).into(), window_end: TimeTuple(9, 20, 0).into(), step: 600 };
+
+    let entries = run_travel_time_matrix(
+        &[fixture::DEN_HAAG], &[fixture::UTRECHT, fixture::LIEGE],
+        timetable, transfers, Arc::new(QueryOptions::default()), &matrix, &db,
+    ).await?;
+    let medians: Vec<_> = entries.iter().map(|e| (e.to_stop, e.median_travel_time, e.reachable)).collect();
+    // Waiting for the 09:20 to Utrecht and the 11:00 to 's-Hertogenbosch is included
+    assert_eq!(medians, [(fixture::UTRECHT, Some(3000), 3), (fixture::LIEGE, Some(14400), 3)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn departure_board() -> anyhow::Result<()> {
+    let today = today();
+    let db = fixture::fixture_db(today).await?;
+
+    let departures = get_departures(fixture::UTRECHT, today, TimeTuple(10, 0, 0), 10, &db).await?;
+    let trips: Vec<_> = departures.iter().map(|d| d.trip_id).collect();
+    // Trip 103 ends here
+    assert_eq!(trips, [100, 101, 102]);
+    assert_eq!(departures[0].platform_code.as_deref(), Some("5"));
+    assert_eq!(departures[0].route_short_name, "IC");
+    assert_eq!(departures[0].headsign, "Amsterdam Centraal");
+    assert_eq!(departures[1].departure_delay, Some(180));
+    assert_eq!(departures[1].expected_departure(), u32::from(TimeTuple(10, 33, 0)));
+    assert!(departures[2].cancelled);
+
+    // The delays in `stop_time` are only used while the trip runs, they may be of another date
+    db.exec("update stop_time set departure_delay = 60 where trip_id = 100 and stop_sequence = 2", vec![]).await?;
+    let delays = |visits: Vec<StopVisit>| -> Vec<_> { visits.iter().map(|v| (v.trip_id, v.departure_delay)).collect() };
+    let from_time = TimeTuple(10, 0, 0).into();
+    let running = get_stop_departures(fixture::UTRECHT, today, from_time, Some(from_time), 2, &db).await?;
+    assert_eq!(delays(running), [(100, Some(60)), (101, Some(180))]);
+    let later = TimeTuple(12, 0, 0).into();
+    let arrived = get_stop_departures(fixture::UTRECHT, today, from_time, Some(later), 2, &db).await?;
+    assert_eq!(delays(arrived), [(100, None), (101, Some(180))]);
+
+    // Cancellations only apply to their own service date
+    let yesterday = DelayRecord {
+        service_date: naive_date_to_date(today.pred_opt().unwrap()),
+        trip_id: 100,
+        stop_sequence: 2,
+        cancelled: true,
+        ..Default::default()
+    };
+    DelayRecord::insert(&db, &yesterday).await?;
+    let departures = get_departures(fixture::UTRECHT, today, TimeTuple(10, 0, 0), 10, &db).await?;
+    assert!(!departures[0].cancelled);
+    // The last observation counts, trip 102 runs after all
+    let runs = DelayRecord {
+        service_date: naive_date_to_date(today),
+        trip_id: 102,
+        stop_sequence: 1,
+        observed_at: rbatis::rbdc::DateTime::now().add(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    DelayRecord::insert(&db, &runs).await?;
+    let departures = get_departures(fixture::UTRECHT, today, TimeTuple(10, 0, 0), 10, &db).await?;
+    assert!(!departures[2].cancelled);
+
+    // A single platform, and the limit
+    let departures = get_departures(fixture::UTRECHT_PLATFORM_5, today, TimeTuple(10, 0, 0), 1, &db).await?;
+    assert_eq!(departures.iter().map(|d| d.trip_id).collect::<Vec<_>>(), [100]);
+
+    // Nothing runs on other days
+    let tomorrow = today.succ_opt().unwrap();
+    assert!(get_departures(fixture::UTRECHT, tomorrow, TimeTuple(10, 0, 0), 10, &db).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn arrival_board() -> anyhow::Result<()> {
+    let today = today();
+    let db = fixture::fixture_db(today).await?;
+
+    let arrivals = get_arrivals(fixture::UTRECHT, today, TimeTuple(9, 0, 0), 10, &db).await?;
+    // Trips 101 and 102 start here, so alighting is not possible
+    let trips: Vec<_> = arrivals.iter().map(|a| a.trip_id).collect();
+    assert_eq!(trips, [100, 103]);
+    assert_eq!(arrivals[0].origin, "Den Haag Centraal");
+    assert_eq!(arrivals[0].platform_code.as_deref(), Some("5"));
+    assert_eq!(arrivals[1].origin, "Amsterdam Centraal");
+    assert_eq!(arrivals[1].platform_code.as_deref(), Some("7"));
+
+    let arrivals = get_arrivals(fixture::AMSTERDAM, today, TimeTuple(10, 30, 0), 10, &db).await?;
+    let trips: Vec<_> = arrivals.iter().map(|a| (a.trip_id, a.cancelled)).collect();
+    assert_eq!(trips, [(101, false), (102, true)]);
+
+    // The delays in `stop_time` are only used while the trip runs, they may be of another date
+    db.exec("update stop_time set arrival_delay = 120 where trip_id = 100 and stop_sequence = 3", vec![]).await?;
+    let from_time = TimeTuple(10, 0, 0).into();
+    let running = get_stop_arrivals(fixture::AMSTERDAM, today, from_time, Some(from_time), 1, &db).await?;
+    assert_eq!(running[0].arrival_delay, Some(120));
+    let later = TimeTuple(12, 0, 0).into();
+    let arrived = get_stop_arrivals(fixture::AMSTERDAM, today, from_time, Some(later), 1, &db).await?;
+    assert_eq!((arrived[0].trip_id, arrived[0].arrival_delay), (100, None));
+    Ok(())
+}
+
+#[tokio::test]
+async fn trip_details() -> anyhow::Result<()> {
+    let today = today();
+    let db = fixture::fixture_db(today).await?;
+
+    let trip = get_trip_details_by_number("2100", today, &db).await?.expect("Trip 2100 runs today");
+    assert_eq!(trip.trip_id, 100);
+    let names: Vec<_> = trip.stops.iter().map(|s| s.stop_name.as_str()).collect();
+    assert_eq!(names, ["Den Haag Centraal", "Utrecht Centraal", "Amsterdam Centraal"]);
+    assert_eq!(trip.stops[1].platform_code.as_deref(), Some("5"));
+    assert!(trip.stops[0].pickup && !trip.stops[0].drop_off);
+    assert!(!trip.stops[2].pickup && trip.stops[2].drop_off);
+    assert_eq!(last_passed_stop(&trip.stops, TimeTuple(9, 0, 0).into()), None);
+    assert_eq!(last_passed_stop(&trip.stops, TimeTuple(10, 5, 0).into()), Some(1));
+
+    // The delay at Utrecht is also expected in Amsterdam
+    let trip = get_trip_details(101, today, &db).await?.expect("Trip 101 exists");
+    assert_eq!(trip.stops[1].arrival_delay, None);
+    assert_eq!(trip.stops[1].predicted_arrival, u32::from(TimeTuple(11, 0, 0)));
+    assert_eq!(last_passed_stop(&trip.stops, TimeTuple(10, 31, 0).into()), None);
+    // Yesterday's trip has no delays in the archive, and does not run now
+    let trip = get_trip_details(101, today.pred_opt().unwrap(), &db).await?.expect("Trip 101 exists");
+    assert_eq!(trip.stops[0].departure_delay, None);
+    assert_eq!(trip.stops[1].predicted_arrival, trip.stops[1].scheduled_arrival);
+
+    // Feed time continues after midnight, and follows the clock when daylight saving time starts
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let after_midnight = TIMEZONE.with_ymd_and_hms(2024, 7, 2, 0, 30, 0).unwrap();
+    assert_eq!(feed_seconds(date, after_midnight), Some(TimeTuple(24, 30, 0).into()));
+    assert_eq!(feed_seconds(date.succ_opt().unwrap(), after_midnight), Some(TimeTuple(0, 30, 0).into()));
+    assert_eq!(feed_seconds(date, TIMEZONE.with_ymd_and_hms(2024, 6, 30, 23, 0, 0).unwrap()), None);
+    let dst = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+    let morning = TIMEZONE.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap();
+    assert_eq!(feed_seconds(dst, morning), Some(TimeTuple(9, 0, 0).into()));
+
+    assert!(get_trip_details_by_number("2100", today.succ_opt().unwrap(), &db).await?.is_none());
+    Ok(())
+}
+
+/// Den Haag - Utrecht with trip 100, then Utrecht - Amsterdam with trip 101
+fn fixture_journey() -> Journey {
+    let location = |stop_id, parent_id| Location { stop_id, parent_id };
+    Journey::from(vec![
+        JourneyPart::Vehicle(TripLeg {
+            from_stop: location(3001, fixture::DEN_HAAG),
+            to_stop: location(fixture::UTRECHT_PLATFORM_5, fixture::UTRECHT),
+            departure: TimeTuple(9, 20, 0).into(),
+            arrival: TimeTuple(10, 0, 0).into(),
+            trip_id: 100,
+            route_id: 0,
+            wheelchair: Accessibility::default(),
+        }),
+        JourneyPart::Transfer(TransferLeg {
+            from_stop: fixture::UTRECHT_PLATFORM_5,
+            to_stop: fixture::UTRECHT_PLATFORM_7,
+            duration: 300,
+            wheelchair_accessible: false,
+            risk: RiskLevel::Unknown,
+        }),
+        JourneyPart::Vehicle(TripLeg {
+            from_stop: location(fixture::UTRECHT_PLATFORM_7, fixture::UTRECHT),
+            to_stop: location(2001, fixture::AMSTERDAM),
+            departure: TimeTuple(10, 30, 0).into(),
+            arrival: TimeTuple(10, 57, 0).into(),
+            trip_id: 101,
+            route_id: 1,
+            wheelchair: Accessibility::default(),
+        }),
+    ])
+}
+
+#[tokio::test]
+async fn serializable_journey_result() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+
+    let result = journey_result(&fixture_journey(), &db).await?;
+    assert_eq!(result.transfers, 1);
+    assert_eq!(result.legs.len(), 3);
+    let Leg::Vehicle(first) = &result.legs[0] else { panic!("Expected a vehicle leg") };
+    assert_eq!(first.from.name, "Den Haag Centraal");
+    assert_eq!(first.to.platform_code.as_deref(), Some("5"));
+    assert_eq!(first.to.parent_id, fixture::UTRECHT);
+    assert_eq!(first.route_short_name, "IC");
+    assert_eq!(first.headsign, "Amsterdam Centraal");
+    let Leg::Transfer(transfer) = &result.legs[1] else { panic!("Expected a transfer") };
+    assert_eq!(transfer.to.platform_code.as_deref(), Some("7"));
+
+    let json = serde_json::to_value(&result)?;
+    assert_eq!(json["legs"][0]["type"], "vehicle");
+    assert_eq!(json["legs"][2]["trip_short_name"], "4800");
+    Ok(())
+}
+
+#[tokio::test]
+async fn journey_geojson() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let result = journey_result(&fixture_journey(), &db).await?;
+
+    let collection = journey_to_geojson(&result, &db).await?;
+    let lines: Vec<_> = collection.features.iter()
+        .filter_map(|f| match &f.geometry.as_ref()?.value {
+            geojson::Value::LineString(line) => Some(line),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(lines.len(), 3);
+    // Trip 100 follows its shape via Leiden up to Utrecht
+    assert_eq!(lines[0], &vec![vec![4.3249, 52.0808], vec![4.4819, 52.1661], vec![5.1100, 52.0894]]);
+    // The transfer and trip 101 (without shape) are straight lines
+    assert_eq!(lines[1].len(), 2);
+    assert_eq!(lines[2], &vec![vec![5.1100, 52.0894], vec![4.9003, 52.3789]]);
+
+    let first = collection.features[0].properties.as_ref().unwrap();
+    assert_eq!(first["type"], "vehicle");
+    assert_eq!(first["departure"], "09:20:00");
+    Ok(())
+}
+
+#[tokio::test]
+async fn leg_polylines() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    update_shape_polylines(&db, &ShapeSettings::default()).await?;
+    // Trip 100 from Den Haag (sequence 1) to Utrecht (sequence 2)
+    let polyline = leg_polyline(100, 1, 2, &db).await?.expect("Trip 100 has a shape");
+    let line = polyline::decode_polyline(&polyline, 5).map_err(anyhow::Error::msg)?;
+    assert_eq!(line.0.len(), 3);
+    assert_eq!((line.0[1].x, line.0[1].y), (4.4819, 52.1661));
+    assert_eq!(leg_polyline(101, 1, 2, &db).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn journey_gpx_kml() -> anyhow::Result<()> {
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let db = fixture::fixture_db(date).await?;
+    let result = journey_result(&fixture_journey(), &db).await?;
+
+    let gpx = journey_to_gpx(&result, date, &db).await?;
+    assert!(gpx.contains("<name>Den Haag Centraal - Amsterdam Centraal</name>"));
+    // Utrecht platform 5 and 7 are separate waypoints
+    assert_eq!(gpx.matches("<wpt ").count(), 4);
+    assert!(gpx.contains("<desc>Platform 1, departure 09:20</desc>"));
+    // Times are in UTC, 09:20 in Amsterdam is 07:20 in summer
+    assert!(gpx.contains(r#"<trkpt lat="52.0808" lon="4.3249"><time>2024-07-01T07:20:00Z</time></trkpt>"#));
+    // Trip 100 follows its shape via Leiden
+    assert!(gpx.contains(r#"<trkpt lat="52.1661" lon="4.4819"/>"#));
+    assert_eq!(gpx.matches("<trkseg>").count(), 3);
+
+    let kml = journey_to_kml(&result, date, &db).await?;
+    assert!(kml.contains("<name>IC 2100 to Amsterdam Centraal</name>"));
+    assert!(kml.contains("<begin>2024-07-01T09:20:00+02:00</begin><end>2024-07-01T10:00:00+02:00</end>"));
+    assert!(kml.contains("4.3249,52.0808 4.4819,52.1661 5.11,52.0894"));
+    assert_eq!(kml.matches("<Placemark>").count(), 7);
+    Ok(())
+}
+
+#[test]
+fn journey_ical() -> anyhow::Result<()> {
+    let result = JourneyResult {
+        departure: Some(TimeTuple(9, 20, 0).into()),
+        arrival: Some(TimeTuple(25, 10, 0).into()),
+        transfers: 0,
+        reliability: None,
+        legs: vec![Leg::Vehicle(VehicleLeg {
+            from: StopInfo {
+                stop_id: 3001,
+                parent_id: fixture::DEN_HAAG,
+                name: "Den Haag Centraal".to_string(),
+                platform_code: Some("1".to_string()),
+                lat: 52.0808,
+                lon: 4.3249,
+            },
+            to: StopInfo {
+                stop_id: 2001,
+                parent_id: fixture::AMSTERDAM,
+                name: "Amsterdam Centraal".to_string(),
+                platform_code: None,
+                lat: 52.3789,
+                lon: 4.9003,
+            },
+            departure: TimeTuple(9, 20, 0).into(),
+            arrival: TimeTuple(25, 10, 0).into(),
+            trip_id: 100,
+            trip_short_name: Some("2100".to_string()),
+            headsign: "Amsterdam Centraal".to_string(),
+            route_id: 10,
+            route_short_name: "IC".to_string(),
+            route_long_name: String::new(),
+            route_type: RouteType::Train,
+            agency_id: "NS".to_string(),
+            route_color: None,
+            route_text_color: None,
+            wheelchair_accessible: false,
+        })],
+    };
+
+    // Winter time (UTC+1), arriving after midnight
+    let ical = journey_to_ical(&result, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap())?;
+    assert_eq!(ical.matches("BEGIN:VEVENT").count(), 1);
+    assert!(ical.contains("DTSTART:20240115T082000Z\r\n"));
+    assert!(ical.contains("DTEND:20240116T001000Z\r\n"));
+    assert!(ical.contains("SUMMARY:IC 2100 to Amsterdam Centraal\r\n"));
+    assert!(ical.contains("LOCATION:Den Haag Centraal\\, platform 1\r\n"));
+    assert!(ical.lines().all(|line| line.len() <= 76));
+
+    // On the day daylight saving time starts, times are relative to noon minus 12 hours
+    let ical = journey_to_ical(&result, NaiveDate::from_ymd_opt(2024, 3, 31).unwrap())?;
+    assert!(ical.contains("DTSTART:20240331T072000Z\r\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn stop_search() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let index = SearchIndex::load(&db).await?;
+    let first = |query: &str| index.search(query, 5).first().map(|r| r.stop_id);
+
+    assert_eq!(normalize(" ’s-Hertogenbosch "), "s hertogenbosch");
+    // Prefix, typos and accents
+    assert_eq!(first("utr"), Some(fixture::UTRECHT));
+    assert_eq!(first("amsterdm"), Some(fixture::AMSTERDAM));
+    assert_eq!(first("s-hertogenbosch"), Some(4000));
+    assert_eq!(first("liege"), Some(5000));
+    // Station codes, IFF names and haltes place codes
+    assert_eq!(first("asd"), Some(fixture::AMSTERDAM));
+    assert_eq!(first("gravenhage"), Some(fixture::DEN_HAAG));
+    assert_eq!(first("UTCS"), Some(fixture::UTRECHT));
+    assert_eq!(index.search("gravenhage", 5)[0].matched, "'s-Gravenhage");
+    // Ranked by number of departures
+    let results = index.search("centraal", 5);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].stop_id, fixture::UTRECHT);
+    assert_eq!(results[0].departures, 3);
+    assert!(index.search("xyz", 5).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn spatial_index() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let stops = StopIndex::load(&db).await?;
+    let stations = StopIndex::load_stations(&db).await?;
+    assert_eq!(stops.len(), 11);
+    assert_eq!(stations.len(), 5);
+
+    // Utrecht Centraal and its platforms
+    let nearby = stops.within_radius(52.0894, 5.1100, 100.0);
+    assert_eq!(nearby.len(), 3);
+    assert!(nearby.iter().all(|s| s.stop.parent_id == fixture::UTRECHT && s.distance < 1.0));
+
+    let nearby = stations.within_radius(52.0894, 5.1100, 40_000.0);
+    let ids: Vec<_> = nearby.iter().map(|s| s.stop.stop_id).collect();
+    assert_eq!(ids, [fixture::UTRECHT, fixture::AMSTERDAM]);
+    let expected = Haversine::distance(Point::new(5.1100, 52.0894), Point::new(4.9003, 52.3789));
+    assert!((nearby[1].distance - expected).abs() < 1.0);
+
+    let nearest = stations.nearest(52.0, 4.4, 2);
+    let ids: Vec<_> = nearest.iter().map(|s| s.stop.stop_id).collect();
+    assert_eq!(ids, [fixture::DEN_HAAG, fixture::UTRECHT]);
+    assert!(nearest[0].distance < nearest[1].distance);
+
+    let mut ids: Vec<_> = stations.in_bbox(52.0, 4.0, 52.5, 5.0).iter().map(|s| s.stop_id).collect();
+    ids.sort();
+    assert_eq!(ids, [fixture::AMSTERDAM, fixture::DEN_HAAG]);
+    assert!(stations.in_bbox(53.0, 4.0, 54.0, 5.0).is_empty());
+    Ok(())
+}
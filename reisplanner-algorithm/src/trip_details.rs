@@ -0,0 +1,120 @@
+use chrono::NaiveDate;
+use rbatis::RBatis;
+use serde::Serialize;
+
+use reisplanner_gtfs::gtfs::types::{PickupType, RouteType};
+
+use crate::board::expected_time;
+use crate::database::queries::{find_trips_by_short_name, get_trip_info, get_trip_stops, TripStopRow};
+use crate::export::{feed_seconds, now};
+
+/// A trip with all of its stops
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TripDetails {
+    pub trip_id: u32,
+    pub trip_short_name: Option<String>,
+    pub headsign: String,
+    pub route_id: u32,
+    pub route_short_name: String,
+    pub route_type: RouteType,
+    pub agency_id: String,
+    pub stops: Vec<TripStop>,
+    /// Index in `stops` of the stop that the vehicle passed most recently,
+    /// if the trip runs on the current or previous service date and has departed
+    pub last_passed: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TripStop {
+    pub stop_id: u32,
+    pub stop_sequence: u32,
+    pub stop_name: String,
+    pub platform_code: Option<String>,
+    /// Times in seconds since midnight of the service date
+    pub scheduled_arrival: u32,
+    pub scheduled_departure: u32,
+    /// Times including the realtime delay
+    pub predicted_arrival: u32,
+    pub predicted_departure: u32,
+    pub arrival_delay: Option<i32>,
+    pub departure_delay: Option<i32>,
+    /// Passengers can board here
+    pub pickup: bool,
+    /// Passengers can alight here
+    pub drop_off: bool,
+    pub cancelled: bool,
+}
+
+/// Get the stops of a trip, with the realtime information of `date` if it is the current service date,
+/// or the previous one, as its trips may run after midnight
+pub async fn get_trip_details(trip_id: u32, date: NaiveDate, db: &RBatis) -> anyhow::Result<Option<TripDetails>> {
+    let Some(trip) = get_trip_info(trip_id, db).await? else {
+        return Ok(None);
+    };
+    let now = now();
+    let realtime = date == now.date_naive() || date.succ_opt() == Some(now.date_naive());
+    let seconds = feed_seconds(date, now).filter(|_| realtime);
+
+    let stops = trip_stops(get_trip_stops(trip_id, date, seconds, db).await?, realtime);
+    let last_passed = seconds.and_then(|seconds| last_passed_stop(&stops, seconds));
+
+    Ok(Some(TripDetails {
+        trip_id,
+        trip_short_name: trip.trip_short_name,
+        headsign: trip.trip_headsign,
+        route_id: trip.route_id,
+        route_short_name: trip.route_short_name,
+        route_type: trip.route_type,
+        agency_id: trip.agency_id,
+        stops,
+        last_passed,
+    }))
+}
+
+/// Get the trip with this `trip_short_name` (e.g. train number) that runs on `date`
+pub async fn get_trip_details_by_number(
+    trip_short_name: &str, date: NaiveDate, db: &RBatis,
+) -> anyhow::Result<Option<TripDetails>> {
+    match find_trips_by_short_name(trip_short_name, date, db).await?.first() {
+        None => Ok(None),
+        Some(&trip_id) => get_trip_details(trip_id, date, db).await,
+    }
+}
+
+/// Convert the database rows. Stops without realtime information
+/// get the last known delay of an earlier stop.
+fn trip_stops(rows: Vec<TripStopRow>, realtime: bool) -> Vec<TripStop> {
+    let mut last_delay = None;
+    rows.into_iter()
+        .map(|row| {
+            let (arrival_delay, departure_delay) = match realtime {
+                true => (row.arrival_delay, row.departure_delay),
+                false => (None, None),
+            };
+            let predicted_arrival = expected_time(row.arrival_time, arrival_delay.or(last_delay));
+            last_delay = departure_delay.or(arrival_delay).or(last_delay);
+            let predicted_departure = expected_time(row.departure_time, departure_delay.or(last_delay));
+            TripStop {
+                stop_id: row.stop_id,
+                stop_sequence: row.stop_sequence,
+                stop_name: row.stop_name,
+                platform_code: row.platform_code,
+                scheduled_arrival: row.arrival_time,
+                scheduled_departure: row.departure_time,
+                predicted_arrival,
+                predicted_departure,
+                arrival_delay,
+                departure_delay,
+                pickup: row.pickup_type != PickupType::NotAvailable,
+                drop_off: row.drop_off_type != PickupType::NotAvailable,
+                cancelled: realtime && row.cancelled,
+            }
+        })
+        .collect()
+}
+
+/// The index of the last stop with a predicted departure before `now`
+/// (seconds since the start of the service date, see `feed_seconds`)
+pub fn last_passed_stop(stops: &[TripStop], now: u32) -> Option<usize> {
+    stops.iter().rposition(|stop| stop.predicted_departure <= now)
+}
@@ -1,4 +1,6 @@
-use crate::algorithms::raptor::{Location, TripLeg};
+use std::collections::HashMap;
+
+use crate::algorithms::raptor::{Location, TransferLeg, TripLeg};
 use crate::getters::get_stop_readable;
 use crate::utils::seconds_to_hms;
 use rbatis::executor::Executor;
@@ -8,11 +10,18 @@ use reisplanner_gtfs::gtfs::types::{Route, Trip};
 pub enum JourneyPart {
     Station(Location),
     Vehicle(TripLeg),
-    // (from, to, duration)
-    Transfer(u32, u32, u32),
+    Transfer(TransferLeg),
 }
 
 impl JourneyPart {
+    /// The trip leg, if this part is travelling in a vehicle
+    pub fn vehicle(&self) -> Option<&TripLeg> {
+        match self {
+            JourneyPart::Vehicle(leg) => Some(leg),
+            _ => None,
+        }
+    }
+
     pub async fn to_string(&self, db: &impl Executor) -> anyhow::Result<String> {
         match self {
             JourneyPart::Station(location) => {
@@ -29,11 +38,75 @@ impl JourneyPart {
                     .ok_or(anyhow::Error::msg("Cannot get route"))?;
                 Ok(format!("From {dep_name} at {dep_time} to {arr_name} at {arr_time} using {} {} {}", route.agency_id, route.route_short_name, route.route_long_name))
             }
-            JourneyPart::Transfer(from, to, duration) => {
-                let from_name = get_stop_readable(from, db).await?;
-                let to_name = get_stop_readable(to, db).await?;
-                Ok(format!("Transfer from {from_name} to {to_name} ({} mins)", duration / 60))
+            JourneyPart::Transfer(transfer) => {
+                let from_name = get_stop_readable(&transfer.from_stop, db).await?;
+                let to_name = get_stop_readable(&transfer.to_stop, db).await?;
+                Ok(format!("Transfer from {from_name} to {to_name} ({} mins)", transfer.duration / 60))
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// A journey found by the planner, from departure to arrival
+#[derive(Clone, Debug)]
+pub struct Journey {
+    pub parts: Vec<JourneyPart>,
+    /// Probability that all transfers will be made, see `reliability::annotate_reliability`
+    pub reliability: Option<f64>,
+}
+
+impl From<Vec<JourneyPart>> for Journey {
+    fn from(parts: Vec<JourneyPart>) -> Self {
+        Self { parts, reliability: None }
+    }
+}
+
+impl Journey {
+    pub fn legs(&self) -> impl Iterator<Item=&TripLeg> {
+        self.parts.iter().filter_map(JourneyPart::vehicle)
+    }
+
+    /// Departure time of the first vehicle
+    pub fn departure(&self) -> Option<u32> {
+        self.legs().next().map(|leg| leg.departure)
+    }
+
+    /// Arrival time of the last vehicle
+    pub fn arrival(&self) -> Option<u32> {
+        self.legs().last().map(|leg| leg.arrival)
+    }
+
+    pub fn transfers(&self) -> usize {
+        self.legs().count().saturating_sub(1)
+    }
+
+    /// Check if this journey is at least as good as `other` in departure time,
+    /// arrival time and number of transfers, and better in at least one of them.
+    pub fn dominates(&self, other: &Journey) -> bool {
+        let (Some(dep), Some(arr)) = (self.departure(), self.arrival()) else { return false };
+        let (Some(other_dep), Some(other_arr)) = (other.departure(), other.arrival()) else { return true };
+        let at_least_as_good = dep >= other_dep && arr <= other_arr
+            && self.transfers() <= other.transfers();
+        let better = dep > other_dep || arr < other_arr || self.transfers() < other.transfers();
+        at_least_as_good && better
+    }
+
+    /// Fraction of the in-vehicle time that is spent on the same trips as in `other`,
+    /// relative to the journey with the least in-vehicle time
+    pub fn similarity(&self, other: &Journey) -> f64 {
+        let in_vehicle = |journey: &Journey| {
+            let mut times = HashMap::new();
+            for leg in journey.legs() {
+                *times.entry(leg.trip_id).or_insert(0) += leg.arrival - leg.departure;
+            }
+            times
+        };
+        let (times, other_times) = (in_vehicle(self), in_vehicle(other));
+        let shared: u32 = times.iter()
+            .filter_map(|(trip_id, &time)| other_times.get(trip_id).map(|&t| time.min(t)))
+            .sum();
+        let total = times.values().sum::<u32>().min(other_times.values().sum());
+        if total == 0 { return 0.0; }
+        shared as f64 / total as f64
+    }
+}
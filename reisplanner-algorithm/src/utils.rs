@@ -23,12 +23,14 @@ pub fn serialize_to_disk<T: Serialize>(data: &T, filename: &str) -> io::Result<(
     Ok(())
 }
 
+/// Read data written by `serialize_to_disk`.
+/// Data that does not match the layout of `T` (e.g. from an older version) is an `InvalidData` error.
 pub fn deserialize_from_disk<T: for<'de> Deserialize<'de>>(filename: &str) -> io::Result<T> {
     let mut file = File::open(filename)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
-    let decoded: T = bincode::deserialize(&buffer).unwrap();
-    Ok(decoded)
+    bincode::deserialize(&buffer)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
 pub fn seconds_to_hms(seconds: u32) -> String {
@@ -0,0 +1,115 @@
+use reisplanner_algorithm::board::Departure;
+use reisplanner_algorithm::export::{clock_time, leg_name};
+use reisplanner_algorithm::reliability::RiskLevel;
+use reisplanner_algorithm::result::{JourneyResult, Leg, StopInfo};
+use reisplanner_algorithm::trip_details::TripDetails;
+
+/// A journey as readable text, with a line for every stop and vehicle
+pub fn format_journey(journey: &JourneyResult) -> String {
+    let mut lines = vec![journey_summary(journey)];
+    for leg in &journey.legs {
+        match leg {
+            Leg::Vehicle(vehicle) => {
+                lines.push(format!("  {}  {}", clock_time(vehicle.departure), stop_text(&vehicle.from)));
+                lines.push(format!("         {}", leg_name(vehicle)));
+                lines.push(format!("  {}  {}", clock_time(vehicle.arrival), stop_text(&vehicle.to)));
+            }
+            Leg::Transfer(transfer) => {
+                let risk = match transfer.risk {
+                    RiskLevel::Medium => ", might be missed",
+                    RiskLevel::High => ", likely to be missed",
+                    RiskLevel::Low | RiskLevel::Unknown => "",
+                };
+                lines.push(format!("         Transfer {}{risk}", duration(transfer.duration)));
+            }
+            Leg::Via(stop) => {
+                lines.push(format!("         Via {}", stop.name));
+            }
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Departure and arrival time, duration, transfers and reliability on one line
+pub fn journey_summary(journey: &JourneyResult) -> String {
+    let (Some(departure), Some(arrival)) = (journey.departure, journey.arrival) else {
+        return "Walk only".to_string();
+    };
+    let transfers = match journey.transfers {
+        0 => "direct".to_string(),
+        1 => "1 transfer".to_string(),
+        n => format!("{n} transfers"),
+    };
+    let reliability = journey.reliability
+        .map(|reliability| format!(", {:.0}% reliable", reliability * 100.0))
+        .unwrap_or_default();
+    format!(
+        "{} - {}  {}  {transfers}{reliability}",
+        clock_time(departure), clock_time(arrival), duration(arrival.saturating_sub(departure)),
+    )
+}
+
+/// A departure board as a table
+pub fn format_departures(station: &str, departures: &[Departure]) -> String {
+    let mut lines = vec![format!("Departures from {station}")];
+    if departures.is_empty() {
+        lines.push("  No departures".to_string());
+    }
+    for departure in departures {
+        lines.push(format!(
+            "  {} {:<9} {:<10} {:<30} {}",
+            clock_time(departure.scheduled_departure),
+            delay_text(departure.departure_delay, departure.cancelled),
+            service_name(&departure.route_short_name, departure.trip_short_name.as_deref()),
+            departure.headsign,
+            departure.platform_code.as_ref().map(|p| format!("platform {p}")).unwrap_or_default(),
+        ).trim_end().to_string());
+    }
+    lines.join("\n") + "\n"
+}
+
+/// All stops of a trip, marking the stop that was passed last
+pub fn format_trip(trip: &TripDetails) -> String {
+    let mut lines = vec![format!(
+        "{} to {}",
+        service_name(&trip.route_short_name, trip.trip_short_name.as_deref()), trip.headsign,
+    )];
+    for (i, stop) in trip.stops.iter().enumerate() {
+        let marker = if trip.last_passed == Some(i) { ">" } else { " " };
+        let arrival = if stop.drop_off { clock_time(stop.scheduled_arrival) } else { "     ".to_string() };
+        let departure = if stop.pickup { clock_time(stop.scheduled_departure) } else { "     ".to_string() };
+        let delay = delay_text(stop.departure_delay.or(stop.arrival_delay), stop.cancelled);
+        let platform = stop.platform_code.as_ref().map(|p| format!(", platform {p}")).unwrap_or_default();
+        lines.push(format!("{marker} {arrival} {departure} {delay:<9} {}{platform}", stop.stop_name));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// E.g. "IC 2100", or only the route name if the trip has no number
+pub fn service_name(route_short_name: &str, trip_short_name: Option<&str>) -> String {
+    format!("{route_short_name} {}", trip_short_name.unwrap_or_default()).trim().to_string()
+}
+
+/// Delay in whole minutes, e.g. "+3", or "cancelled"
+pub fn delay_text(delay: Option<i32>, cancelled: bool) -> String {
+    if cancelled {
+        return "cancelled".to_string();
+    }
+    match delay.map(|d| d / 60) {
+        Some(minutes) if minutes > 0 => format!("+{minutes}"),
+        Some(minutes) if minutes < 0 => format!("{minutes}"),
+        _ => String::new(),
+    }
+}
+
+/// Duration as H:MM
+pub fn duration(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 3600, seconds % 3600 / 60)
+}
+
+fn stop_text(stop: &StopInfo) -> String {
+    match &stop.platform_code {
+        Some(platform) => format!("{}, platform {platform}", stop.name),
+        None => stop.name.clone(),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod format;
+pub mod planner;
+pub mod tui;
+#[cfg(test)]
+mod tests;
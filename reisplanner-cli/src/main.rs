@@ -0,0 +1,147 @@
+use std::env;
+
+use anyhow::anyhow;
+use clap::{Parser, Subcommand};
+use rbatis::RBatis;
+use tracing_subscriber::EnvFilter;
+
+use reisplanner_algorithm::algorithms::raptor::QueryOptions;
+use reisplanner_algorithm::board::get_departures;
+use reisplanner_algorithm::database::new_db_connection;
+use reisplanner_algorithm::search::SearchIndex;
+use reisplanner_algorithm::trip_details::get_trip_details_by_number;
+use reisplanner_cli::format::{format_departures, format_journey, format_trip};
+use reisplanner_cli::planner::{parse_date, parse_date_time, resolve_station, Planner};
+use reisplanner_cli::tui;
+use reisplanner_gtfs::gtfs::types::RouteType;
+
+/// Plan journeys and show departure boards and trips from the command line.
+/// The database can be set with `DATABASE_URL` (e.g. `sqlite://fixture.db`).
+#[derive(Parser)]
+#[command(name = "reisplanner")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plan journeys between two stations, e.g. `plan "Utrecht Centraal" Groningen --at 08:30`
+    Plan {
+        /// Station name, code or id
+        from: String,
+        /// Station name, code or id
+        to: String,
+        /// Departure (or arrival) time as 2026-10-20T08:30 or 08:30, defaults to now
+        #[arg(long)]
+        at: Option<String>,
+        /// Arrive before `--at` instead of departing after it
+        #[arg(long)]
+        arrive_by: bool,
+        /// Do not use buses
+        #[arg(long)]
+        no_bus: bool,
+        /// Only use trains
+        #[arg(long)]
+        train_only: bool,
+        /// Only use accessible vehicles and stops
+        #[arg(long)]
+        wheelchair: bool,
+        /// Number of journeys
+        #[arg(long, default_value_t = 3)]
+        count: usize,
+    },
+    /// Show the departure board of a station
+    Departures {
+        /// Station name, code or id
+        station: String,
+        /// Date and time as 2026-10-20T08:30 or 08:30, defaults to now
+        #[arg(long)]
+        at: Option<String>,
+        /// Number of departures
+        #[arg(long, default_value_t = 15)]
+        limit: usize,
+    },
+    /// Show all stops of a trip by its number, e.g. a train number
+    Trip {
+        /// Trip number, e.g. 2100
+        number: String,
+        /// Date as 2026-10-20, defaults to today
+        #[arg(long)]
+        date: Option<String>,
+    },
+    /// Interactive terminal UI with station search, journeys and a live departure board
+    Tui,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let log_level = EnvFilter::try_from_default_env()
+        .unwrap_or(EnvFilter::new("error"));
+    tracing_subscriber::fmt().with_env_filter(log_level).with_writer(std::io::stderr).init();
+
+    let cli = Cli::parse();
+    // The timetable cache is shared, so it is only used for the default database
+    let cache = env::var("DATABASE_URL").is_err();
+    let db = match env::var("DATABASE_URL") {
+        Ok(url) => {
+            let db = RBatis::new();
+            db.init(rbdc_sqlite::driver::SqliteDriver {}, &url)?;
+            db
+        }
+        Err(_) => new_db_connection()?,
+    };
+
+    match cli.command {
+        Command::Plan { from, to, at, arrive_by, no_bus, train_only, wheelchair, count } => {
+            let planner = Planner::load(db, cache).await?;
+            let from = planner.resolve(&from)?;
+            let to = planner.resolve(&to)?;
+            let (date, time) = parse_date_time(at.as_deref())?;
+            let mut options = if train_only { QueryOptions::train_only() } else { QueryOptions::default() };
+            if no_bus {
+                options.excluded_route_types.insert(RouteType::Bus);
+            }
+            options.wheelchair = wheelchair;
+
+            let journeys = planner.plan(from.stop_id, to.stop_id, date, time, arrive_by, &options, count).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&journeys)?);
+            } else if journeys.is_empty() {
+                return Err(anyhow!("No journeys found from {} to {}", from.name, to.name));
+            } else {
+                println!("{} to {}\n", from.name, to.name);
+                let text: Vec<String> = journeys.iter().map(format_journey).collect();
+                print!("{}", text.join("\n"));
+            }
+        }
+        Command::Departures { station, at, limit } => {
+            let station = resolve_station(&SearchIndex::load(&db).await?, &station)?;
+            let (date, time) = parse_date_time(at.as_deref())?;
+            let departures = get_departures(station.stop_id, date, time, limit, &db).await?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&departures)?);
+            } else {
+                print!("{}", format_departures(&station.name, &departures));
+            }
+        }
+        Command::Trip { number, date } => {
+            let date = parse_date(date.as_deref())?;
+            let trip = get_trip_details_by_number(&number, date, &db).await?
+                .ok_or(anyhow!("No trip {number} on {date}"))?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&trip)?);
+            } else {
+                print!("{}", format_trip(&trip));
+            }
+        }
+        Command::Tui => {
+            let planner = Planner::load(db, cache).await?;
+            tui::run(&planner).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use rbatis::RBatis;
+use tracing::debug;
+
+use reisplanner_algorithm::algorithms::raptor;
+use reisplanner_algorithm::algorithms::raptor::{QueryOptions, RRoute};
+use reisplanner_algorithm::algorithms::raptor::alternatives::{run_raptor_alternatives, run_raptor_arrive_by, AlternativesOptions};
+use reisplanner_algorithm::database::queries::get_active_services;
+use reisplanner_algorithm::export::{now, today};
+use reisplanner_algorithm::reliability::annotate_reliability;
+use reisplanner_algorithm::result::{journey_results, JourneyResult};
+use reisplanner_algorithm::search::{SearchIndex, SearchResult};
+
+/// Everything needed to plan journeys, loaded once
+pub struct Planner {
+    pub db: RBatis,
+    pub timetable: HashMap<u32, RRoute>,
+    pub transfer_times: HashMap<u32, u32>,
+    pub search: SearchIndex,
+}
+
+impl Planner {
+    /// Load the timetable, from the cache on disk if `cache` is set, and the search index
+    pub async fn load(db: RBatis, cache: bool) -> anyhow::Result<Self> {
+        debug!("Loading timetable...");
+        let timetable = raptor::get_timetable(&db, cache).await?;
+        let transfer_times = raptor::generate_transfer_times(&db).await?;
+        let search = SearchIndex::load(&db).await?;
+        Ok(Self { db, timetable, transfer_times, search })
+    }
+
+    pub fn resolve(&self, query: &str) -> anyhow::Result<SearchResult> {
+        resolve_station(&self.search, query)
+    }
+
+    /// Plan up to `count` journeys on `date` departing after `time`, or arriving before it if `arrive_by` is set
+    #[allow(clippy::too_many_arguments)]
+    pub async fn plan(
+        &self,
+        from: u32,
+        to: u32,
+        date: NaiveDate,
+        time: u32,
+        arrive_by: bool,
+        options: &QueryOptions,
+        count: usize,
+    ) -> anyhow::Result<Vec<JourneyResult>> {
+        let options = &QueryOptions { services: Some(get_active_services(date, &self.db).await?), ..options.clone() };
+        let alternatives = AlternativesOptions { count, ..Default::default() };
+        let mut journeys = if arrive_by {
+            run_raptor_arrive_by(
+                from, to, time, &self.timetable, &self.transfer_times, options, &alternatives, &self.db,
+            ).await?
+        } else {
+            run_raptor_alternatives(
+                from, to, time, &self.timetable, &self.transfer_times, options, &alternatives, &self.db,
+            ).await?
+        };
+        for journey in &mut journeys {
+            annotate_reliability(journey, &self.db).await?;
+        }
+        journey_results(&journeys, &self.db).await
+    }
+}
+
+/// Resolve a station name, code or id to the best matching station
+pub fn resolve_station(search: &SearchIndex, query: &str) -> anyhow::Result<SearchResult> {
+    if let Ok(stop_id) = query.trim().parse::<u32>() {
+        if let Some(result) = search.get(stop_id) {
+            return Ok(result);
+        }
+    }
+    search.search(query, 1).into_iter().next()
+        .ok_or(anyhow!("No station found for \"{query}\""))
+}
+
+/// Parse a date and time as `2026-10-20T08:30`, `2026-10-20 08:30` or `08:30` (today).
+/// Returns the date and the time in seconds since midnight, defaulting to now.
+pub fn parse_date_time(value: Option<&str>) -> anyhow::Result<(NaiveDate, u32)> {
+    let Some(value) = value else {
+        let now = now();
+        return Ok((now.date_naive(), now.num_seconds_from_midnight()));
+    };
+    let value = value.trim();
+    let date_time = ["%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok());
+    if let Some(date_time) = date_time {
+        return Ok((date_time.date(), date_time.time().num_seconds_from_midnight()));
+    }
+    let time = NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| anyhow!("Invalid time {value}, expected e.g. 2026-10-20T08:30 or 08:30"))?;
+    Ok((today(), time.num_seconds_from_midnight()))
+}
+
+/// Parse a date as `2026-10-20`, defaulting to today
+pub fn parse_date(value: Option<&str>) -> anyhow::Result<NaiveDate> {
+    match value {
+        None => Ok(today()),
+        Some(value) => NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .map_err(|_| anyhow!("Invalid date {value}, expected e.g. 2026-10-20")),
+    }
+}
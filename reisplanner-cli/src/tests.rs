@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+
+use reisplanner_algorithm::algorithms::raptor::QueryOptions;
+use reisplanner_algorithm::export::today;
+use reisplanner_algorithm::fixture;
+use reisplanner_gtfs::utils::TimeTuple;
+
+use crate::format::{delay_text, format_journey};
+use crate::planner::{parse_date_time, Planner};
+use crate::tui::app::{Action, App, Focus};
+use crate::tui::refresh_board;
+use crate::tui::ui::render;
+
+#[test]
+fn date_time_arguments() -> anyhow::Result<()> {
+    let date = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+    assert_eq!(parse_date_time(Some("2026-10-20T08:30"))?, (date, 8 * 3600 + 30 * 60));
+    assert_eq!(parse_date_time(Some("08:30"))?.1, 8 * 3600 + 30 * 60);
+    assert!(parse_date_time(Some("tomorrow")).is_err());
+    assert_eq!(parse_date_time(Some("08:30"))?.0, today());
+    Ok(())
+}
+
+#[test]
+fn delay_texts() {
+    assert_eq!(delay_text(Some(190), false), "+3");
+    assert_eq!(delay_text(Some(30), false), "");
+    assert_eq!(delay_text(None, true), "cancelled");
+}
+
+#[tokio::test]
+async fn plan_by_name() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let planner = Planner::load(db, false).await?;
+    let from = planner.resolve("den haag")?;
+    let to = planner.resolve("2000")?;
+    assert_eq!(from.stop_id, fixture::DEN_HAAG);
+    assert_eq!(to.name, "Amsterdam Centraal");
+    assert!(planner.resolve("nowhere").is_err());
+
+    let options = QueryOptions::default();
+    let journeys = planner.plan(from.stop_id, to.stop_id, today(), TimeTuple(9, 0, 0).into(), false, &options, 3).await?;
+    let text = format_journey(&journeys[0]);
+    assert!(text.starts_with("09:20 - 10:27  1:07  direct"));
+    assert!(text.contains("  09:20  Den Haag Centraal, platform 1\n"));
+    assert!(text.contains("         IC 2100 to Amsterdam Centraal\n"));
+
+    // Arrive by 10:30 from Utrecht: trip 100 departing 10:02, not the earlier departure
+    let arrival = TimeTuple(10, 30, 0).into();
+    let journeys = planner.plan(fixture::UTRECHT, to.stop_id, today(), arrival, true, &options, 3).await?;
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(journeys[0].departure, Some(TimeTuple(10, 2, 0).into()));
+
+    // The fixture only has service today
+    let tomorrow = today().succ_opt().unwrap();
+    assert!(planner.plan(from.stop_id, to.stop_id, tomorrow, TimeTuple(9, 0, 0).into(), false, &options, 3).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn terminal_ui() -> anyhow::Result<()> {
+    let db = fixture::fixture_db(today()).await?;
+    let planner = Planner::load(db, false).await?;
+    let mut app = App::default();
+    let press = |app: &mut App, code: KeyCode| app.handle_key(KeyEvent::from(code), &planner.search);
+
+    // Autocomplete the origin, choosing the second suggestion
+    for c in "centraal".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    assert_eq!(app.from.suggestions.len(), 3);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(press(&mut app, KeyCode::Enter), Action::None);
+    assert!(app.from.suggestions.is_empty());
+    assert_ne!(app.from.station.as_ref().map(|s| s.stop_id), Some(fixture::UTRECHT));
+    assert_eq!(app.focus, Focus::To);
+
+    // Change the origin to Den Haag, using a typo
+    press(&mut app, KeyCode::BackTab);
+    for _ in 0..app.from.text.len() {
+        press(&mut app, KeyCode::Backspace);
+    }
+    for c in "den hagg".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.from.station.as_ref().map(|s| s.stop_id), Some(fixture::DEN_HAAG));
+    for c in "asd".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    assert_eq!(press(&mut app, KeyCode::Enter), Action::Plan);
+
+    refresh_board(&mut app, &planner).await;
+    let journeys = planner.plan(
+        fixture::DEN_HAAG, fixture::AMSTERDAM, today(), TimeTuple(9, 0, 0).into(), false, &QueryOptions::default(), 3,
+    ).await?;
+    app.set_journeys(journeys);
+    assert_eq!(app.focus, Focus::Journeys);
+    press(&mut app, KeyCode::Enter);
+    assert!(app.expanded.contains(&0));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 20))?;
+    terminal.draw(|frame| render(frame, &mut app))?;
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    assert!(screen.contains("Den Haag Centraal"));
+    assert!(screen.contains("▾ 09:20 - 10:27"));
+    assert!(screen.contains("IC 2100 to Amsterdam Centraal"));
+    assert!(screen.contains("Departures Den Haag Centraal"));
+
+    assert_eq!(press(&mut app, KeyCode::Esc), Action::Quit);
+    Ok(())
+}
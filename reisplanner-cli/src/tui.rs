@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+use chrono::Timelike;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+use reisplanner_algorithm::algorithms::raptor::QueryOptions;
+use reisplanner_algorithm::board::get_departures;
+use reisplanner_algorithm::export::now;
+
+use crate::planner::Planner;
+use crate::tui::app::{Action, App};
+
+pub mod app;
+pub mod ui;
+
+/// How often the departure board is reloaded, to show new realtime delays
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to wait for a key press before checking if the board should be refreshed
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const BOARD_SIZE: usize = 30;
+const JOURNEYS: usize = 5;
+
+/// Run the terminal UI until the user quits
+pub async fn run(planner: &Planner) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, planner).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, planner: &Planner) -> anyhow::Result<()> {
+    let mut app = App::default();
+    let mut board_refreshed: Option<Instant> = None;
+    loop {
+        let board_changed = app.from.station.as_ref().map(|s| s.stop_id)
+            != app.board_station.as_ref().map(|s| s.stop_id);
+        let board_outdated = board_refreshed.is_some_and(|time| time.elapsed() >= REFRESH_INTERVAL);
+        if board_changed || board_outdated {
+            refresh_board(&mut app, planner).await;
+            board_refreshed = Some(Instant::now());
+        }
+
+        terminal.draw(|frame| ui::render(frame, &mut app))?;
+        if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key, &planner.search) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Plan => {
+                app.status = "Planning...".to_string();
+                terminal.draw(|frame| ui::render(frame, &mut app))?;
+                plan(&mut app, planner).await;
+            }
+        }
+    }
+}
+
+/// Plan journeys between the chosen stations, departing now
+pub async fn plan(app: &mut App, planner: &Planner) {
+    let (Some(from), Some(to)) = (&app.from.station, &app.to.station) else { return };
+    let now = now();
+    let time = now.num_seconds_from_midnight();
+    match planner.plan(from.stop_id, to.stop_id, now.date_naive(), time, false, &QueryOptions::default(), JOURNEYS).await {
+        Ok(journeys) => app.set_journeys(journeys),
+        Err(e) => app.status = format!("Planning failed: {e}"),
+    }
+}
+
+/// Load the departures from the origin, including realtime delays
+pub async fn refresh_board(app: &mut App, planner: &Planner) {
+    app.board_station = app.from.station.clone();
+    let Some(station) = &app.board_station else {
+        app.board.clear();
+        return;
+    };
+    let now = now();
+    match get_departures(station.stop_id, now.date_naive(), now.num_seconds_from_midnight(), BOARD_SIZE, &planner.db).await {
+        Ok(departures) => {
+            app.board = departures;
+            app.board_updated = now.format("%H:%M:%S").to_string();
+        }
+        Err(e) => app.status = format!("Could not load departures: {e}"),
+    }
+}
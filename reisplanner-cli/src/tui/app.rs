@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::widgets::ListState;
+
+use reisplanner_algorithm::board::Departure;
+use reisplanner_algorithm::result::JourneyResult;
+use reisplanner_algorithm::search::{SearchIndex, SearchResult};
+
+/// Number of autocomplete suggestions
+const SUGGESTIONS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Focus {
+    #[default]
+    From,
+    To,
+    Journeys,
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Plan,
+    Quit,
+}
+
+/// A station input with autocomplete suggestions
+#[derive(Debug, Default)]
+pub struct StationInput {
+    pub text: String,
+    pub suggestions: Vec<SearchResult>,
+    pub selected: usize,
+    /// The chosen suggestion
+    pub station: Option<SearchResult>,
+}
+
+impl StationInput {
+    fn edit(&mut self, search: &SearchIndex) {
+        self.station = None;
+        self.suggestions = search.search(&self.text, SUGGESTIONS);
+        self.selected = 0;
+    }
+
+    /// Choose the selected suggestion, returns false if there is none
+    fn choose(&mut self) -> bool {
+        let Some(station) = self.suggestions.get(self.selected).cloned() else {
+            return self.station.is_some();
+        };
+        self.text = station.name.clone();
+        self.station = Some(station);
+        self.suggestions.clear();
+        true
+    }
+
+    fn select(&mut self, offset: isize) {
+        if !self.suggestions.is_empty() {
+            self.selected = self.selected.saturating_add_signed(offset).min(self.suggestions.len() - 1);
+        }
+    }
+}
+
+/// State of the terminal UI
+#[derive(Debug, Default)]
+pub struct App {
+    pub focus: Focus,
+    pub from: StationInput,
+    pub to: StationInput,
+    pub journeys: Vec<JourneyResult>,
+    pub journey_list: ListState,
+    /// Indices of the journeys of which the legs are shown
+    pub expanded: HashSet<usize>,
+    /// Departure board of the origin
+    pub board: Vec<Departure>,
+    pub board_station: Option<SearchResult>,
+    /// Time the board was last refreshed, as HH:MM:SS
+    pub board_updated: String,
+    pub status: String,
+}
+
+impl App {
+    pub fn handle_key(&mut self, key: KeyEvent, search: &SearchIndex) -> Action {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if control => return Action::Quit,
+            KeyCode::Tab => self.focus = self.next_focus(),
+            KeyCode::BackTab => self.focus = match self.focus {
+                Focus::From => Focus::Journeys,
+                Focus::To => Focus::From,
+                Focus::Journeys => Focus::To,
+            },
+            _ => return match self.focus {
+                Focus::From | Focus::To => self.handle_input_key(key, search),
+                Focus::Journeys => self.handle_journeys_key(key),
+            },
+        }
+        Action::None
+    }
+
+    fn next_focus(&self) -> Focus {
+        match self.focus {
+            Focus::From => Focus::To,
+            Focus::To if self.journeys.is_empty() => Focus::From,
+            Focus::To => Focus::Journeys,
+            Focus::Journeys => Focus::From,
+        }
+    }
+
+    fn input(&mut self) -> &mut StationInput {
+        match self.focus {
+            Focus::To => &mut self.to,
+            _ => &mut self.from,
+        }
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent, search: &SearchIndex) -> Action {
+        match key.code {
+            KeyCode::Char(c) => {
+                self.input().text.push(c);
+                self.input().edit(search);
+            }
+            KeyCode::Backspace => {
+                self.input().text.pop();
+                self.input().edit(search);
+            }
+            KeyCode::Up => self.input().select(-1),
+            KeyCode::Down => self.input().select(1),
+            KeyCode::Enter => {
+                if !self.input().choose() {
+                    self.status = "No station found".to_string();
+                    return Action::None;
+                }
+                self.status.clear();
+                if self.from.station.is_some() && self.to.station.is_some() {
+                    return Action::Plan;
+                }
+                self.focus = self.next_focus();
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_journeys_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Up => self.journey_list.select_previous(),
+            KeyCode::Down => self.journey_list.select_next(),
+            KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right => {
+                if let Some(selected) = self.journey_list.selected() {
+                    if !self.expanded.remove(&selected) {
+                        self.expanded.insert(selected);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Show newly planned journeys, with the first one selected
+    pub fn set_journeys(&mut self, journeys: Vec<JourneyResult>) {
+        self.status = match journeys.len() {
+            0 => "No journeys found".to_string(),
+            _ => String::new(),
+        };
+        self.journey_list.select((!journeys.is_empty()).then_some(0));
+        self.journeys = journeys;
+        self.expanded.clear();
+        if !self.journeys.is_empty() {
+            self.focus = Focus::Journeys;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use reisplanner_algorithm::export::clock_time;
+
+use crate::format::{delay_text, format_journey, journey_summary, service_name};
+use crate::tui::app::{App, Focus, StationInput};
+
+const HELP: &str = "Tab: next field  ↑↓: select  Enter: choose / show legs  Esc: quit";
+
+pub fn render(frame: &mut Frame, app: &mut App) {
+    let [inputs, main, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [from, to] = Layout::horizontal([Constraint::Fill(1); 2]).areas(inputs);
+    let [results, board] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(main);
+
+    render_input(frame, from, "From", &app.from, app.focus == Focus::From);
+    render_input(frame, to, "To", &app.to, app.focus == Focus::To);
+
+    let input = match app.focus {
+        Focus::From => Some(&app.from),
+        Focus::To => Some(&app.to),
+        Focus::Journeys => None,
+    };
+    match input {
+        Some(input) if !input.suggestions.is_empty() => render_suggestions(frame, results, input),
+        _ => render_journeys(frame, results, app),
+    }
+    render_board(frame, board, app);
+
+    let footer_text = if app.status.is_empty() { HELP } else { app.status.as_str() };
+    frame.render_widget(Paragraph::new(footer_text).dark_gray(), footer);
+}
+
+fn block(title: String, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(title);
+    if focused { block.border_style(Style::new().fg(Color::Yellow)) } else { block }
+}
+
+fn render_input(frame: &mut Frame, area: Rect, title: &str, input: &StationInput, focused: bool) {
+    let style = if input.station.is_some() { Style::new().bold() } else { Style::new() };
+    frame.render_widget(
+        Paragraph::new(input.text.as_str()).style(style).block(block(title.to_string(), focused)),
+        area,
+    );
+    if focused {
+        let x = area.x + 1 + input.text.chars().count() as u16;
+        frame.set_cursor_position(Position::new(x.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+}
+
+fn render_suggestions(frame: &mut Frame, area: Rect, input: &StationInput) {
+    let items: Vec<ListItem> = input.suggestions.iter()
+        .map(|suggestion| {
+            let mut line = Line::from(suggestion.name.clone());
+            if suggestion.matched != suggestion.name {
+                line.push_span(Span::from(format!("  ({})", suggestion.matched)).dark_gray());
+            }
+            ListItem::new(line)
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(input.selected));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(block("Stations".to_string(), false))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        area,
+        &mut state,
+    );
+}
+
+fn render_journeys(frame: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = app.journeys.iter().enumerate()
+        .map(|(i, journey)| {
+            let marker = if app.expanded.contains(&i) { "▾" } else { "▸" };
+            let mut text = Text::from(format!("{marker} {}", journey_summary(journey)));
+            if app.expanded.contains(&i) {
+                // The legs, without the summary line
+                for line in format_journey(journey).lines().skip(1) {
+                    text.push_line(Line::from(line.to_string()).dark_gray());
+                }
+            }
+            ListItem::new(text)
+        })
+        .collect();
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(block("Journeys".to_string(), app.focus == Focus::Journeys))
+            .highlight_style(Style::new().add_modifier(Modifier::BOLD).fg(Color::Yellow)),
+        area,
+        &mut app.journey_list,
+    );
+}
+
+fn render_board(frame: &mut Frame, area: Rect, app: &App) {
+    let title = match &app.board_station {
+        Some(station) => format!("Departures {} ({})", station.name, app.board_updated),
+        None => "Departures".to_string(),
+    };
+    let lines: Vec<Line> = app.board.iter()
+        .map(|departure| {
+            let delay = delay_text(departure.departure_delay, departure.cancelled);
+            let delay_style = if departure.cancelled || departure.departure_delay.unwrap_or(0) >= 60 {
+                Style::new().fg(Color::Red)
+            } else {
+                Style::new()
+            };
+            let mut spans = vec![
+                Span::from(format!("{} ", clock_time(departure.scheduled_departure))),
+                Span::styled(format!("{delay:<9} "), delay_style),
+                Span::from(format!(
+                    "{:<9} {}",
+                    service_name(&departure.route_short_name, departure.trip_short_name.as_deref()),
+                    departure.headsign,
+                )),
+            ];
+            if let Some(platform) = &departure.platform_code {
+                spans.push(Span::from(format!("  {platform}")).dark_gray());
+            }
+            let line = Line::from(spans);
+            if departure.cancelled { line.crossed_out() } else { line }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block(title, false)), area);
+}
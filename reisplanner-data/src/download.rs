@@ -70,7 +70,7 @@ pub async fn download_zip(url: &str, folder_path: &str) -> Result<Vec<PathBuf>,
         let path = Path::new(folder_path);
         let filename = url
             .split('/')
-            .last()
+            .next_back()
             .and_then(|name| name.strip_suffix(".gz"))
             .expect("Filename should end with .gz");
         let file_path = path.join(filename); 
@@ -8,6 +8,8 @@ pub struct StationTransfer {
     pub station_code: String,
     /// Transfer time in minutes
     pub transfer_time: u32,
+    /// Full name of the station, used for searching
+    pub station_name: String,
 }
 
 impl From<Station> for StationTransfer {
@@ -15,6 +17,7 @@ impl From<Station> for StationTransfer {
         StationTransfer {
             station_code: value.station_abr,
             transfer_time: value.transfer_time,
+            station_name: value.station_name,
         }
     }
 }
@@ -0,0 +1,128 @@
+use std::env;
+
+use rbatis::RBatis;
+use tracing::{debug, instrument};
+
+const DEFAULT_RETENTION_DAYS: u32 = 365;
+const DEFAULT_COMPACT_AFTER_DAYS: u32 = 2;
+
+/// Settings for the historical delay archive (`delay_record` table)
+#[derive(Debug, Clone)]
+pub struct ArchiveSettings {
+    /// Delete records with a service date older than this amount of days
+    pub retention_days: u32,
+    /// Only keep the last observation of each stop of a trip
+    /// for service dates older than this amount of days
+    pub compact_after_days: u32,
+}
+
+impl Default for ArchiveSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: DEFAULT_RETENTION_DAYS,
+            compact_after_days: DEFAULT_COMPACT_AFTER_DAYS,
+        }
+    }
+}
+
+impl ArchiveSettings {
+    /// Read the settings from the `ARCHIVE_RETENTION_DAYS` and
+    /// `ARCHIVE_COMPACT_AFTER_DAYS` environment variables, using defaults if unset
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut settings = Self::default();
+        if let Ok(days) = env::var("ARCHIVE_RETENTION_DAYS") {
+            settings.retention_days = days.parse()?;
+        }
+        if let Ok(days) = env::var("ARCHIVE_COMPACT_AFTER_DAYS") {
+            settings.compact_after_days = days.parse()?;
+        }
+        Ok(settings)
+    }
+}
+
+/// Apply the retention and compaction settings to the delay archive
+#[instrument(skip(db))]
+pub async fn maintain_archive(db: &RBatis, settings: &ArchiveSettings) -> anyhow::Result<()> {
+    let deleted = remove_expired(db, settings.retention_days).await?;
+    debug!("Removed {deleted} expired delay records");
+    let compacted = compact(db, settings.compact_after_days).await?;
+    debug!("Removed {compacted} delay records by compaction");
+    Ok(())
+}
+
+/// Remove all records with a service date older than `days` days
+async fn remove_expired(db: &RBatis, days: u32) -> anyhow::Result<u64> {
+    let result = db.exec(
+        format!("delete from delay_record
+            where service_date < date('now', '-{days} days')").as_str(),
+        vec![],
+    ).await?;
+    Ok(result.rows_affected)
+}
+
+/// Only keep the last observation per service date, trip and stop
+/// for service dates older than `days` days
+async fn compact(db: &RBatis, days: u32) -> anyhow::Result<u64> {
+    let result = db.exec(
+        format!("delete from delay_record
+            where service_date < date('now', '-{days} days')
+            and rowid not in (
+                select max(rowid) from delay_record
+                where service_date < date('now', '-{days} days')
+                group by service_date, trip_id, stop_sequence
+            )").as_str(),
+        vec![],
+    ).await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, Utc};
+    use rbatis::RBatis;
+    use reisplanner_gtfs::gtfs::types::DelayRecord;
+    use reisplanner_gtfs::utils::naive_date_to_date;
+    use reisplanner_utils::database::sync_table;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn archive_retention_and_compaction() -> anyhow::Result<()> {
+        let db = RBatis::new();
+        db.init(rbdc_sqlite::driver::SqliteDriver {}, "sqlite://:memory:")?;
+        db.get_pool()?.set_max_open_conns(1).await;
+        sync_table::<DelayRecord>(&db, "delay_record").await?;
+
+        // Two observations of the same stop, on service dates older than the retention,
+        // older than the compaction and of today. SQLite compares with the date in UTC.
+        let today = Utc::now().date_naive();
+        let settings = ArchiveSettings::default();
+        let dates = [
+            today - Days::new(settings.retention_days as u64 + 1),
+            today - Days::new(settings.compact_after_days as u64 + 1),
+            today,
+        ];
+        let records: Vec<DelayRecord> = dates.iter()
+            .flat_map(|&date| (0..2).map(move |i| DelayRecord {
+                service_date: naive_date_to_date(date),
+                trip_id: 1,
+                stop_sequence: 1,
+                arrival_delay: Some(i * 60),
+                ..Default::default()
+            }))
+            .collect();
+        DelayRecord::insert_batch(&db, &records, records.len() as u64).await?;
+
+        maintain_archive(&db, &settings).await?;
+        let remaining: Vec<_> = DelayRecord::select_all(&db).await?.into_iter()
+            .map(|r| (r.service_date.to_string(), r.arrival_delay))
+            .collect();
+        let date = |date| naive_date_to_date(date).to_string();
+        assert_eq!(remaining, [
+            (date(dates[1]), Some(60)),
+            (date(today), Some(0)),
+            (date(today), Some(60)),
+        ]);
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use std::env;
+use std::fs::File;
+use std::io::{stdout, Write};
+
+use anyhow::{anyhow, Context};
+use chrono::NaiveDate;
+use tracing_subscriber::EnvFilter;
+
+use reisplanner_gtfs::statistics::{get_statistics, write_report, ReportFormat, StatisticsOptions};
+use reisplanner_utils::database::new_db_connection;
+
+const USAGE: &str = "Usage: punctuality [--by route|stop|agency|hour] [--format csv|json] \
+[--from YYYY-MM-DD] [--to YYYY-MM-DD] [--threshold SECONDS] [--output FILE]";
+
+/// Generate punctuality reports from the delay archive
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let log_level = EnvFilter::try_from_default_env()
+        .unwrap_or(EnvFilter::new("error,reisplanner=debug"));
+    tracing_subscriber::fmt().with_env_filter(log_level).with_writer(std::io::stderr).init();
+
+    let mut options = StatisticsOptions::default();
+    let mut format = ReportFormat::Csv;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(anyhow!("Missing value for {arg}\n{USAGE}"));
+        match arg.as_str() {
+            "--by" => options.group_by = value()?.parse()?,
+            "--format" => format = value()?.parse()?,
+            "--from" => options.from = Some(parse_date(&value()?)?),
+            "--to" => options.to = Some(parse_date(&value()?)?),
+            "--threshold" => options.on_time_threshold = value()?.parse()?,
+            "--output" => output = Some(value()?),
+            _ => return Err(anyhow!("Unknown argument {arg}\n{USAGE}")),
+        }
+    }
+
+    let db = new_db_connection()?;
+    let statistics = get_statistics(&db, &options).await?;
+
+    let writer: Box<dyn Write> = match output {
+        None => Box::new(stdout()),
+        Some(path) => Box::new(File::create(path)?),
+    };
+    write_report(&statistics, format, writer)
+}
+
+fn parse_date(value: &str) -> anyhow::Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").with_context(|| format!("Invalid date {value}"))
+}
@@ -1,6 +1,6 @@
 use rbatis::RBatis;
 use tracing::{debug, instrument, trace};
-use reisplanner_gtfs::gtfs::types::{Agency, CalendarDate, FeedInfo, LastUpdated, Route, Shape, Stop, StopTime, Transfer, Trip};
+use reisplanner_gtfs::gtfs::types::{Agency, CalendarDate, DelayRecord, FeedInfo, LastUpdated, Route, Shape, ShapePolyline, Stop, StopTime, Transfer, Trip};
 use reisplanner_utils::database::{add_index, new_db_connection, sync_table};
 
 #[instrument]
@@ -14,11 +14,13 @@ pub async fn init_db() -> anyhow::Result<RBatis> {
     sync_table::<FeedInfo>(&rb, "feed_info").await?;
     sync_table::<Route>(&rb, "route").await?;
     sync_table::<Shape>(&rb, "shape").await?;
+    sync_table::<ShapePolyline>(&rb, "shape_polyline").await?;
     sync_table::<Stop>(&rb, "stop").await?;
     sync_table::<StopTime>(&rb, "stop_time").await?;
     sync_table::<Transfer>(&rb, "transfer").await?;
     sync_table::<Trip>(&rb, "trip").await?;
     sync_table::<LastUpdated>(&rb, "last_updated").await?;
+    sync_table::<DelayRecord>(&rb, "delay_record").await?;
 
     Ok(rb)
 }
@@ -36,6 +38,12 @@ pub async fn add_indices(rb: &RBatis) -> anyhow::Result<Vec<String>> {
     names.push(add_index(rb, "stop_time", &["id"]).await?);
     names.push(add_index(rb, "route", &["route_id"]).await?);
     names.push(add_index(rb, "stop", &["stop_id"]).await?);
+    names.push(add_index(rb, "shape", &["shape_id"]).await?);
+    names.push(add_index(rb, "shape_polyline", &["shape_id"]).await?);
+    names.push(add_index(rb, "delay_record", &["trip_id", "stop_id"]).await?);
+    names.push(add_index(rb, "delay_record", &["route_id", "stop_id"]).await?);
+    names.push(add_index(rb, "delay_record", &["stop_id"]).await?);
+    names.push(add_index(rb, "delay_record", &["service_date", "trip_id", "stop_sequence"]).await?);
 
     Ok(names)
 }
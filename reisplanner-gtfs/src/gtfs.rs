@@ -20,7 +20,7 @@ use DownloadError::ParseRemoteModified;
 use crate::errors::{DownloadError, GtfsError};
 use crate::errors::DownloadError::{FileSystem, ParseLocalModified};
 use crate::errors::ParseError::Csv;
-use crate::gtfs::types::{Agency, CalendarDate, FeedInfo, Route, Shape, Stop, StopTime, Transfer, Trip};
+use crate::gtfs::types::{Agency, CalendarDate, FeedInfo, Route, Shape, ShapePolyline, Stop, StopTime, Transfer, Trip};
 use crate::rbatis_wrapper::DatabaseModel;
 
 pub mod types;
@@ -251,6 +251,8 @@ async fn download_parse_gtfs(db: &RBatis) -> Result<(), GtfsError> {
     gtfs_to_db::<Shape>(&transaction, format!("{FOLDER}/shapes.txt").as_str()).await?;
     gtfs_to_db::<StopTime>(&transaction, format!("{FOLDER}/stop_times.txt").as_str()).await?;
     add_stop_time_ids(&transaction).await?;
+    // The simplified shapes are rebuilt from the new shapes
+    ShapePolyline::delete_all(&transaction).await?;
 
     transaction.commit().await?;
 
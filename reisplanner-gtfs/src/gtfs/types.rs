@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use async_trait::async_trait;
 use rbatis::{impl_select, impl_update, rbdc};
 use rbatis::executor::Executor;
@@ -8,7 +10,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::crud_trait;
 use crate::rbatis_wrapper::DatabaseModel;
-use crate::utils::{deserialize_date, deserialize_time_tuple, TimeTuple};
+use crate::utils::{deserialize_bool, deserialize_date, deserialize_time_tuple, TimeTuple};
 
 // Struct for agency.txt
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -54,7 +56,7 @@ pub struct FeedInfo {
 }
 crud_trait!(FeedInfo {});
 
-#[derive(Deserialize_repr, Serialize_repr, PartialEq, Debug, Default, Clone)]
+#[derive(Deserialize_repr, Serialize_repr, PartialEq, Eq, Hash, Debug, Default, Clone, Copy)]
 #[repr(u8)]
 pub enum RouteType {
     #[default]
@@ -70,6 +72,26 @@ pub enum RouteType {
     MonoRail = 12,
 }
 
+impl FromStr for RouteType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tram" => Ok(Self::Tram),
+            "metro" | "subway" => Ok(Self::Metro),
+            "train" | "rail" => Ok(Self::Train),
+            "bus" => Ok(Self::Bus),
+            "ferry" => Ok(Self::Ferry),
+            "cable_tram" => Ok(Self::CableTram),
+            "lift" => Ok(Self::Lift),
+            "funicular" => Ok(Self::Funicular),
+            "trolleybus" => Ok(Self::TrolleyBus),
+            "monorail" => Ok(Self::MonoRail),
+            _ => Err(anyhow::anyhow!("Unknown route type {s}")),
+        }
+    }
+}
+
 // Struct for routes.txt
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Route {
@@ -129,6 +151,21 @@ impl Default for Shape {
 
 crud_trait!(Shape {});
 
+/// A shape simplified with Douglas-Peucker, not part of the GTFS feed
+/// but derived from `shape` by [`crate::shapes::update_shape_polylines`]
+#[derive(Default, Debug, Deserialize, Serialize, Clone)]
+pub struct ShapePolyline {
+    pub shape_id: u32,
+    /// Encoded polyline (precision 5) of the simplified shape
+    pub polyline: String,
+    /// Comma separated `shape_dist_traveled` of each point of the polyline
+    pub distances: String,
+    /// Tolerance in meters that was used for the simplification
+    pub tolerance: f64,
+}
+
+crud_trait!(ShapePolyline {});
+
 
 #[derive(Deserialize_repr, Serialize_repr, Default, PartialEq, Debug, Clone)]
 #[repr(u8)]
@@ -330,7 +367,7 @@ impl Default for Transfer {
 
 crud_trait!(Transfer {});
 
-#[derive(Deserialize_repr, Serialize_repr, Default, PartialEq, Debug, Clone)]
+#[derive(Deserialize_repr, Serialize_repr, Default, PartialEq, Eq, Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum AllowedType {
     #[default]
@@ -388,4 +425,50 @@ impl_select!(Trip {
 pub struct LastUpdated {
     pub last_updated: rbdc::DateTime,
 }
-crud_trait!(LastUpdated {});
\ No newline at end of file
+crud_trait!(LastUpdated {});
+
+/// Realtime delays of a stop of a trip, as observed at `observed_at`.
+/// Unlike the delays in `StopTime`, these are never overwritten,
+/// so they form an archive of historical delays.
+/// Records are identified by `service_date`, `trip_id`, `stop_sequence` and `observed_at`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DelayRecord {
+    pub service_date: Date,
+    pub trip_id: u32,
+    pub stop_sequence: u32,
+    pub observed_at: rbdc::DateTime,
+    pub route_id: u32,
+    pub stop_id: u32,
+    /// Scheduled times in seconds since the start of the service date
+    pub scheduled_arrival: u32,
+    pub scheduled_departure: u32,
+    /// Predicted times in seconds since the start of the service date
+    pub predicted_arrival: Option<u32>,
+    pub predicted_departure: Option<u32>,
+    pub arrival_delay: Option<i32>,
+    pub departure_delay: Option<i32>,
+    /// The stop is skipped or the whole trip is cancelled
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub cancelled: bool,
+}
+
+impl Default for DelayRecord {
+    fn default() -> Self {
+        Self {
+            service_date: Default::default(),
+            trip_id: Default::default(),
+            stop_sequence: Default::default(),
+            observed_at: rbdc::DateTime::now(),
+            route_id: Default::default(),
+            stop_id: Default::default(),
+            scheduled_arrival: Default::default(),
+            scheduled_departure: Default::default(),
+            predicted_arrival: Some(Default::default()),
+            predicted_departure: Some(Default::default()),
+            arrival_delay: Some(Default::default()),
+            departure_delay: Some(Default::default()),
+            cancelled: Default::default(),
+        }
+    }
+}
+crud_trait!(DelayRecord {});
\ No newline at end of file
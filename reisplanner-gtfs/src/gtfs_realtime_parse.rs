@@ -4,6 +4,8 @@ use std::io::Read;
 use std::io::Write;
 use std::time::SystemTime;
 
+use chrono::Utc;
+
 use protobuf::{EnumOrUnknown, Message};
 use rbatis::{RBatis, rbdc};
 use rbatis::executor::Executor;
@@ -13,10 +15,12 @@ use tracing::{debug, instrument, trace, warn};
 
 use reisplanner_gtfs::errors::{DownloadError, FieldParseError, GtfsError, ParseError};
 use reisplanner_gtfs::gtfs::get_contact_info;
-use reisplanner_gtfs::gtfs::types::{LastUpdated, StopTime, Trip};
+use reisplanner_gtfs::gtfs::types::{DelayRecord, LastUpdated, StopTime, Trip};
 use reisplanner_gtfs::gtfs_realtime::gtfs_realtime::{FeedEntity, FeedMessage};
 use reisplanner_gtfs::gtfs_realtime::gtfs_realtime::feed_header::Incrementality::FULL_DATASET;
-use reisplanner_gtfs::utils::{parse_optional_int, parse_optional_int_option};
+use reisplanner_gtfs::gtfs_realtime::gtfs_realtime::trip_descriptor::ScheduleRelationship as TripRelationship;
+use reisplanner_gtfs::gtfs_realtime::gtfs_realtime::trip_update::stop_time_update::ScheduleRelationship as StopRelationship;
+use reisplanner_gtfs::utils::{naive_date_to_date, parse_date, parse_optional_int, parse_optional_int_option, TIMEZONE};
 
 async fn download_gtfs_realtime(url: &String, file_path: &String, last_updated: Option<SystemTime>) -> Result<(), DownloadError> {
     trace!("Downloading realtime GTFS data {url} to {file_path}");
@@ -76,6 +80,34 @@ async fn parse_gtfs_realtime(file_path: &String, db: &dyn Executor) -> Result<()
     Ok(())
 }
 
+/// Add a delay to a scheduled time (both in seconds)
+fn predicted_time(scheduled: u32, delay: Option<i32>) -> Option<u32> {
+    delay.map(|delay| (scheduled as i64 + delay as i64).max(0) as u32)
+}
+
+fn delay_record(
+    stop_time: &StopTime,
+    route_id: u32,
+    service_date: &rbdc::Date,
+    observed_at: &rbdc::DateTime,
+) -> DelayRecord {
+    DelayRecord {
+        service_date: service_date.clone(),
+        trip_id: stop_time.trip_id,
+        stop_sequence: stop_time.stop_sequence,
+        observed_at: observed_at.clone(),
+        route_id,
+        stop_id: stop_time.stop_id,
+        scheduled_arrival: stop_time.arrival_time.into(),
+        scheduled_departure: stop_time.departure_time.into(),
+        predicted_arrival: None,
+        predicted_departure: None,
+        arrival_delay: None,
+        departure_delay: None,
+        cancelled: false,
+    }
+}
+
 async fn parse_gtfs_realtime_entry(entry: &FeedEntity, db: &dyn Executor) -> Result<(), GtfsError> {
     if let Some(trip_update) = entry.trip_update.as_ref() {
         let trip_id = parse_optional_int(trip_update.trip.trip_id.as_ref(), "trip_id");
@@ -88,6 +120,24 @@ async fn parse_gtfs_realtime_entry(entry: &FeedEntity, db: &dyn Executor) -> Res
 
         let trip_id = trip_id
             .map_err(|e| ParseError::Realtime(e, Box::new(trip_update.clone())))?;
+        let route_id = Trip::select_by_id(db, &trip_id).await?
+            .map(|trip| trip.route_id);
+        let observed_at = rbdc::DateTime::now();
+        let service_date = trip_update.trip.start_date.as_ref()
+            .and_then(|date| parse_date(date).ok())
+            .unwrap_or_else(|| naive_date_to_date(Utc::now().with_timezone(&TIMEZONE).date_naive()));
+        let mut delay_records = Vec::new();
+
+        // Archive all stops of cancelled trips as cancelled, the archive holds the cancellations per service date
+        let trip_relationship = trip_update.trip.schedule_relationship
+            .and_then(|r| r.enum_value().ok());
+        if let (Some(TripRelationship::CANCELED), Some(route_id)) = (trip_relationship, route_id) {
+            for stop_time in StopTime::select_by_trip_id(db, &trip_id).await? {
+                let mut record = delay_record(&stop_time, route_id, &service_date, &observed_at);
+                record.cancelled = true;
+                delay_records.push(record);
+            }
+        }
 
         for (i, update) in trip_update.stop_time_update.iter().enumerate() {
             let stop_id: Option<u32> = parse_optional_int_option(update.stop_id.as_ref(), "stop_id")
@@ -122,8 +172,24 @@ async fn parse_gtfs_realtime_entry(entry: &FeedEntity, db: &dyn Executor) -> Res
                     db_stop_time.departure_delay = delay;
                 }
                 StopTime::update_by_id_and_trip(db, db_stop_time, &stop_id, &trip_id).await?;
+
+                // Also archive the update
+                if let Some(route_id) = route_id {
+                    let mut record = delay_record(db_stop_time, route_id, &service_date, &observed_at);
+                    record.arrival_delay = update.arrival.delay;
+                    record.departure_delay = update.departure.delay;
+                    record.predicted_arrival = predicted_time(record.scheduled_arrival, record.arrival_delay);
+                    record.predicted_departure = predicted_time(record.scheduled_departure, record.departure_delay);
+                    record.cancelled = trip_relationship == Some(TripRelationship::CANCELED)
+                        || update.schedule_relationship
+                        .and_then(|r| r.enum_value().ok()) == Some(StopRelationship::SKIPPED);
+                    delay_records.push(record);
+                }
             }
         }
+        if !delay_records.is_empty() {
+            DelayRecord::insert_batch(db, &delay_records, delay_records.len() as u64).await?;
+        }
         // Experimental delay field, delay in stop_time_update takes precedent
         // TODO use current departure or arrival delay instead if possible
         if let Some(delay) = trip_update.delay {
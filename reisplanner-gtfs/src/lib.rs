@@ -1,7 +1,11 @@
 pub mod gtfs_realtime;
 pub mod gtfs;
 pub mod errors;
+pub mod statistics;
+pub mod shapes;
 #[doc(hidden)]
 pub mod rbatis_wrapper;
 #[doc(hidden)]
 pub mod utils;
+#[cfg(test)]
+mod tests;
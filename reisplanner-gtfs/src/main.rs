@@ -6,11 +6,14 @@ use tokio::time::{Duration as TokioDuration, sleep};
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 use reisplanner_gtfs::gtfs::run_gtfs;
+use reisplanner_gtfs::shapes::{ShapeSettings, update_shape_polylines};
 use reisplanner_utils::database::drop_indices;
+use crate::archive::{ArchiveSettings, maintain_archive};
 use crate::database::{add_indices, init_db};
 use crate::gtfs_realtime_parse::run_gtfs_realtime;
 
 mod gtfs_realtime_parse;
+mod archive;
 pub mod database;
 
 
@@ -32,6 +35,8 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    let archive_settings = ArchiveSettings::from_env()?;
+    let shape_settings = ShapeSettings::from_env()?;
     let db = init_db().await?;
     if only_db { 
         // If only db add indices now
@@ -44,8 +49,12 @@ async fn main() -> anyhow::Result<()> {
     run_gtfs(&db).await?;
     // Add indices (after insertion)
     let mut indices = add_indices(&db).await?;
+    // Simplify the shapes (if needed)
+    update_shape_polylines(&db, &shape_settings).await?;
     // Run realtime updates
     run_gtfs_realtime(&db).await?;
+    // Clean up the delay archive
+    maintain_archive(&db, &archive_settings).await?;
 
     info!("Starting update loop");
     let mut previous_run = Utc::now().naive_utc();
@@ -68,6 +77,14 @@ async fn main() -> anyhow::Result<()> {
             }
             // Add indices back again
             indices = add_indices(&db).await?;
+            let result = update_shape_polylines(&db, &shape_settings).await;
+            if let Err(e) = result {
+                error!("Error in shape simplification {e:?}");
+            }
+            let result = maintain_archive(&db, &archive_settings).await;
+            if let Err(e) = result {
+                error!("Error in delay archive maintenance {e:?}");
+            }
         }
 
         previous_run = now;
@@ -0,0 +1,141 @@
+use std::env;
+
+use geo::{Coord, LineString, SimplifyIdx};
+use itertools::Itertools;
+use rbatis::RBatis;
+use serde::Deserialize;
+use tracing::{debug, instrument};
+
+use crate::gtfs::types::{Shape, ShapePolyline};
+
+const DEFAULT_TOLERANCE: f64 = 5.0;
+/// Polyline precision, as used by Google and OpenTripPlanner
+const PRECISION: u32 = 5;
+/// Amount of shapes to load from the database at once
+const CHUNK_SIZE: usize = 500;
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Settings for the simplified shapes (`shape_polyline` table)
+#[derive(Debug, Clone)]
+pub struct ShapeSettings {
+    /// Maximum distance in meters between the simplified and the original shape
+    pub tolerance: f64,
+}
+
+impl Default for ShapeSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+}
+
+impl ShapeSettings {
+    /// Read the settings from the `SHAPE_TOLERANCE` environment variable, using defaults if unset
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut settings = Self::default();
+        if let Ok(tolerance) = env::var("SHAPE_TOLERANCE") {
+            settings.tolerance = tolerance.parse()?;
+        }
+        Ok(settings)
+    }
+}
+
+impl ShapePolyline {
+    /// Decode the points of the polyline, together with their `shape_dist_traveled`
+    pub fn points(&self) -> anyhow::Result<Vec<(Coord, Option<f64>)>> {
+        let line = polyline::decode_polyline(&self.polyline, PRECISION)
+            .map_err(|e| anyhow::anyhow!("Invalid polyline for shape {}: {e}", self.shape_id))?;
+        let distances = self.distances.split(',')
+            .map(|d| if d.is_empty() { Ok(None) } else { d.parse().map(Some) })
+            .collect::<Result<Vec<_>, _>>()?;
+        if distances.len() != line.0.len() {
+            return Err(anyhow::anyhow!("Polyline and distances of shape {} do not match", self.shape_id));
+        }
+        Ok(line.0.into_iter().zip(distances).collect())
+    }
+}
+
+/// Encode coordinates (longitude as x, latitude as y) as a polyline
+pub fn encode_polyline(coords: impl IntoIterator<Item = Coord>) -> anyhow::Result<String> {
+    polyline::encode_coordinates(coords, PRECISION)
+        .map_err(|e| anyhow::anyhow!("Could not encode polyline: {e}"))
+}
+
+/// Simplify the points of a shape (ordered by sequence) with Douglas-Peucker.
+/// `tolerance` is in meters, the points are projected around the first point
+/// so the tolerance is the same in both directions.
+pub fn simplify_shape(shape_id: u32, points: &[Shape], tolerance: f64) -> anyhow::Result<ShapePolyline> {
+    let coords: Vec<Coord> = points.iter()
+        .map(|p| Coord { x: p.shape_pt_lon, y: p.shape_pt_lat })
+        .collect();
+    let scale = coords.first()
+        .map_or(1.0, |c| c.y.to_radians().cos());
+    let projected: LineString = coords.iter()
+        .map(|c| Coord { x: c.x * scale * METERS_PER_DEGREE, y: c.y * METERS_PER_DEGREE })
+        .collect();
+
+    let indices = projected.simplify_idx(&tolerance);
+    let polyline = encode_polyline(indices.iter().map(|&i| coords[i]))?;
+    let distances = indices.iter()
+        .map(|&i| points[i].shape_dist_traveled.map(|d| d.to_string()).unwrap_or_default())
+        .join(",");
+
+    Ok(ShapePolyline { shape_id, polyline, distances, tolerance })
+}
+
+#[derive(Deserialize)]
+struct Count {
+    count: u64,
+}
+
+/// (Re)build the simplified shapes if the shapes or the tolerance have changed.
+/// The simplified shapes are removed when the static GTFS is parsed.
+#[instrument(skip(db))]
+pub async fn update_shape_polylines(db: &RBatis, settings: &ShapeSettings) -> anyhow::Result<()> {
+    let shapes: Vec<Count> = db
+        .query_decode("select count(distinct shape_id) as count from shape", vec![])
+        .await?;
+    let polylines: Vec<Count> = db
+        .query_decode(
+            "select count(*) as count from shape_polyline where tolerance = ?",
+            vec![rbs::to_value!(settings.tolerance)],
+        ).await?;
+    if shapes.first().map(|c| c.count) == polylines.first().map(|c| c.count) {
+        debug!("Simplified shapes are up to date");
+        return Ok(());
+    }
+
+    debug!("Simplifying shapes, this may take a while...");
+    #[derive(Deserialize)]
+    struct ShapeId {
+        shape_id: u32,
+    }
+    let shape_ids: Vec<ShapeId> = db
+        .query_decode("select distinct shape_id from shape order by shape_id", vec![])
+        .await?;
+
+    let mut transaction = db.acquire_begin().await?;
+    ShapePolyline::delete_all(&transaction).await?;
+    for chunk in shape_ids.chunks(CHUNK_SIZE) {
+        let ids = chunk.iter().map(|s| s.shape_id).join(",");
+        let points: Vec<Shape> = transaction
+            .query_decode(
+                format!("select * from shape where shape_id in ({ids})
+                    order by shape_id, shape_pt_sequence").as_str(),
+                vec![],
+            ).await?;
+
+        let polylines = points.into_iter()
+            .chunk_by(|p| p.shape_id)
+            .into_iter()
+            .map(|(shape_id, points)| {
+                simplify_shape(shape_id, &points.collect::<Vec<_>>(), settings.tolerance)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        ShapePolyline::insert_batch(&transaction, &polylines, CHUNK_SIZE as u64).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+/// Delays up to this amount of seconds are considered on time
+pub const DEFAULT_ON_TIME_THRESHOLD: i32 = 180;
+
+/// What to group the statistics by
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GroupBy {
+    Route,
+    Stop,
+    Agency,
+    /// Hour of the day of the scheduled arrival
+    Hour,
+}
+
+impl FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "route" => Ok(Self::Route),
+            "stop" => Ok(Self::Stop),
+            "agency" => Ok(Self::Agency),
+            "hour" => Ok(Self::Hour),
+            _ => Err(anyhow::anyhow!("Unknown grouping {s}, expected route, stop, agency or hour")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatisticsOptions {
+    pub group_by: GroupBy,
+    /// Maximum delay in seconds that is still considered on time
+    pub on_time_threshold: i32,
+    /// First service date to include
+    pub from: Option<NaiveDate>,
+    /// Last service date to include
+    pub to: Option<NaiveDate>,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        Self {
+            group_by: GroupBy::Route,
+            on_time_threshold: DEFAULT_ON_TIME_THRESHOLD,
+            from: None,
+            to: None,
+        }
+    }
+}
+
+/// A single archived stop visit, using the last observation of the day
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub route_id: u32,
+    pub stop_id: u32,
+    pub agency_id: String,
+    pub scheduled_arrival: u32,
+    /// Arrival delay in seconds, or the departure delay if unknown
+    pub delay: Option<i32>,
+    pub cancelled: bool,
+}
+
+impl Observation {
+    fn key(&self, group_by: GroupBy) -> String {
+        match group_by {
+            GroupBy::Route => self.route_id.to_string(),
+            GroupBy::Stop => self.stop_id.to_string(),
+            GroupBy::Agency => self.agency_id.clone(),
+            GroupBy::Hour => format!("{:02}", self.scheduled_arrival / 3600 % 24),
+        }
+    }
+}
+
+/// Punctuality statistics of one group. Delays are in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DelayStatistics {
+    pub key: String,
+    /// Amount of stop visits, including cancelled ones
+    pub observations: usize,
+    pub average_delay: Option<f64>,
+    pub median_delay: Option<i32>,
+    pub p90_delay: Option<i32>,
+    pub p95_delay: Option<i32>,
+    /// Percentage of the visits that were not cancelled and on time
+    pub on_time_percentage: Option<f64>,
+    /// Percentage of the visits that were cancelled
+    pub cancellation_rate: f64,
+}
+
+#[derive(Deserialize)]
+struct ObservationRow {
+    route_id: u32,
+    stop_id: u32,
+    agency_id: String,
+    scheduled_arrival: u32,
+    delay: Option<i32>,
+    cancelled: i64,
+}
+
+/// Get the last observation of every stop visit in the delay archive
+pub async fn get_observations(
+    db: &RBatis, from: Option<NaiveDate>, to: Option<NaiveDate>,
+) -> anyhow::Result<Vec<Observation>> {
+    let mut filter = String::from("1 = 1");
+    if let Some(from) = from {
+        filter += &format!(" and d.service_date >= '{}'", from.format("%Y-%m-%d"));
+    }
+    if let Some(to) = to {
+        filter += &format!(" and d.service_date <= '{}'", to.format("%Y-%m-%d"));
+    }
+
+    let rows: Vec<ObservationRow> = db
+        .query_decode(
+            format!("select d.route_id, d.stop_id, r.agency_id, d.scheduled_arrival,
+                coalesce(d.arrival_delay, d.departure_delay) as delay, d.cancelled,
+                max(d.observed_at) as observed_at
+                from delay_record d join route r on d.route_id = r.route_id
+                where {filter}
+                group by d.service_date, d.trip_id, d.stop_sequence").as_str(),
+            vec![],
+        ).await?;
+
+    Ok(rows.into_iter().map(|row| Observation {
+        route_id: row.route_id,
+        stop_id: row.stop_id,
+        agency_id: row.agency_id,
+        scheduled_arrival: row.scheduled_arrival,
+        delay: row.delay,
+        cancelled: row.cancelled != 0,
+    }).collect())
+}
+
+/// Get the punctuality statistics from the delay archive
+pub async fn get_statistics(db: &RBatis, options: &StatisticsOptions) -> anyhow::Result<Vec<DelayStatistics>> {
+    let observations = get_observations(db, options.from, options.to).await?;
+    Ok(summarise(&observations, options))
+}
+
+/// Group the observations and compute the statistics for each group
+pub fn summarise(observations: &[Observation], options: &StatisticsOptions) -> Vec<DelayStatistics> {
+    let mut groups: BTreeMap<String, Vec<&Observation>> = BTreeMap::new();
+    for observation in observations {
+        groups.entry(observation.key(options.group_by)).or_default().push(observation);
+    }
+
+    groups.into_iter()
+        .map(|(key, group)| group_statistics(key, &group, options.on_time_threshold))
+        .collect()
+}
+
+fn group_statistics(key: String, group: &[&Observation], on_time_threshold: i32) -> DelayStatistics {
+    let cancelled = group.iter().filter(|o| o.cancelled).count();
+    let mut delays: Vec<i32> = group.iter()
+        .filter(|o| !o.cancelled)
+        .filter_map(|o| o.delay)
+        .collect();
+    delays.sort();
+
+    let on_time = delays.iter().filter(|&&d| d <= on_time_threshold).count();
+    let average_delay = (!delays.is_empty())
+        .then(|| delays.iter().map(|&d| d as f64).sum::<f64>() / delays.len() as f64);
+    // Cancelled visits count as not on time
+    let on_time_percentage = (!delays.is_empty() || cancelled > 0)
+        .then(|| on_time as f64 / (delays.len() + cancelled) as f64 * 100.0);
+
+    DelayStatistics {
+        key,
+        observations: group.len(),
+        average_delay,
+        median_delay: percentile(&delays, 50.0),
+        p90_delay: percentile(&delays, 90.0),
+        p95_delay: percentile(&delays, 95.0),
+        on_time_percentage,
+        cancellation_rate: cancelled as f64 / group.len() as f64 * 100.0,
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[i32], percentile: f64) -> Option<i32> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+pub fn write_csv<W: Write>(statistics: &[DelayStatistics], writer: W) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in statistics {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_json<W: Write>(statistics: &[DelayStatistics], writer: W) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(writer, statistics)?;
+    Ok(())
+}
+
+/// Output format of the statistics reports
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!("Unknown format {s}, expected csv or json")),
+        }
+    }
+}
+
+pub fn write_report<W: Write>(statistics: &[DelayStatistics], format: ReportFormat, writer: W) -> anyhow::Result<()> {
+    match format {
+        ReportFormat::Csv => write_csv(statistics, writer),
+        ReportFormat::Json => write_json(statistics, writer),
+    }
+}
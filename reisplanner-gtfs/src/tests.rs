@@ -0,0 +1,71 @@
+use geo::Coord;
+
+use crate::gtfs::types::{Shape, ShapePolyline};
+use crate::shapes::{encode_polyline, simplify_shape};
+use crate::statistics::{summarise, GroupBy, Observation, StatisticsOptions};
+
+#[test]
+fn punctuality_statistics() {
+    let observation = |route_id, hour: u32, delay, cancelled| Observation {
+        route_id, stop_id: 1, agency_id: "NS".to_string(),
+        scheduled_arrival: hour * 3600, delay, cancelled,
+    };
+    let observations = [
+        observation(1, 8, Some(0), false),
+        observation(1, 8, Some(60), false),
+        observation(1, 9, Some(600), false),
+        observation(1, 9, None, true),
+        observation(2, 8, Some(-30), false),
+    ];
+
+    let by_route = summarise(&observations, &StatisticsOptions::default());
+    assert_eq!(by_route.len(), 2);
+    let route = &by_route[0];
+    assert_eq!(route.key, "1");
+    assert_eq!(route.observations, 4);
+    assert_eq!(route.average_delay, Some(220.0));
+    assert_eq!(route.median_delay, Some(60));
+    assert_eq!(route.p95_delay, Some(600));
+    assert_eq!(route.on_time_percentage, Some(50.0));
+    assert_eq!(route.cancellation_rate, 25.0);
+
+    let options = StatisticsOptions { group_by: GroupBy::Hour, ..Default::default() };
+    let by_hour = summarise(&observations, &options);
+    assert_eq!(by_hour.iter().map(|s| s.key.as_str()).collect::<Vec<_>>(), ["08", "09"]);
+    assert_eq!(by_hour[0].observations, 3);
+}
+
+#[test]
+fn simplified_shapes() -> anyhow::Result<()> {
+    // A straight line north with a deviation of about 1 meter halfway
+    let points: Vec<Shape> = [(52.0, 5.0), (52.001, 5.0), (52.002, 5.000015), (52.003, 5.0), (52.003, 5.01)]
+        .into_iter().enumerate()
+        .map(|(i, (shape_pt_lat, shape_pt_lon))| Shape {
+            shape_id: 1,
+            shape_pt_sequence: i as u32 + 1,
+            shape_pt_lat,
+            shape_pt_lon,
+            shape_dist_traveled: Some(i as f64 * 100.0),
+        })
+        .collect();
+    let simplified = simplify_shape(1, &points, 5.0)?;
+    assert_eq!(simplified.distances, "0,300,400");
+    let decoded = simplified.points()?;
+    let expected = [((5.0, 52.0), 0.0), ((5.0, 52.003), 300.0), ((5.01, 52.003), 400.0)];
+    assert_eq!(decoded.iter().map(|(c, d)| ((c.x, c.y), d.unwrap())).collect::<Vec<_>>(), expected);
+    // The deviation is kept with a smaller tolerance
+    assert_eq!(simplify_shape(1, &points, 0.5)?.points()?.len(), 5);
+
+    // Encoding and decoding keeps 5 decimals, points without a distance are kept
+    let coords = [Coord { x: 4.32491, y: 52.08081 }, Coord { x: -0.12345, y: 51.5 }];
+    let polyline = ShapePolyline {
+        shape_id: 2,
+        polyline: encode_polyline(coords)?,
+        distances: "0,".to_string(),
+        tolerance: 5.0,
+    };
+    assert_eq!(polyline.points()?, [(coords[0], Some(0.0)), (coords[1], None)]);
+    let mismatch = ShapePolyline { distances: "0".to_string(), ..polyline };
+    assert!(mismatch.points().is_err());
+    Ok(())
+}
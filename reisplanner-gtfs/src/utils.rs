@@ -4,12 +4,17 @@ use std::num::ParseIntError;
 use std::str::FromStr;
 
 use chrono::{Datelike, NaiveDate};
+use chrono_tz::Europe::Amsterdam;
+use chrono_tz::Tz;
 use rbatis::rbdc;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{SeqAccess, Visitor};
 
 use crate::errors::FieldParseError;
 
+/// Time zone of the GTFS feed
+pub const TIMEZONE: Tz = Amsterdam;
+
 #[derive(Deserialize, Debug, Copy, Clone, Default)]
 pub struct TimeTuple (pub u8, pub u8, pub u8);
 
@@ -92,6 +97,20 @@ where
 }
 
 
+/// Parse a GTFS date in the format YYYYMMDD
+pub fn parse_date(value: &str) -> Result<rbdc::Date, chrono::ParseError> {
+    let chronos_date = NaiveDate::parse_from_str(value, "%Y%m%d")?;
+    Ok(naive_date_to_date(chronos_date))
+}
+
+pub fn naive_date_to_date(date: NaiveDate) -> rbdc::Date {
+    rbdc::Date(fastdate::Date {
+        day: date.day() as u8,
+        mon: date.month() as u8,
+        year: date.year(),
+    })
+}
+
 pub fn deserialize_date<'de, D>(deserializer: D) -> Result<rbdc::Date, D::Error>
 where
     D: Deserializer<'de>,
@@ -116,14 +135,7 @@ where
         where
             E: de::Error,
         {
-            let chronos_date = NaiveDate::parse_from_str(value, "%Y%m%d")
-                .map_err(de::Error::custom)?;
-            let date = fastdate::Date {
-                day: chronos_date.day() as u8,
-                mon: chronos_date.month() as u8,
-                year: chronos_date.year(),
-            };
-            Ok(rbdc::Date(date))
+            parse_date(value).map_err(de::Error::custom)
         }
 
         fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
@@ -137,6 +149,55 @@ where
     deserializer.deserialize_any(DateVisitor)
 }
 
+/// Deserialize a boolean that may be stored as an integer (as sqlite does) or be missing
+pub fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct BoolVisitor;
+
+    impl<'de> Visitor<'de> for BoolVisitor {
+        type Value = bool;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a boolean, an integer or null")
+        }
+
+        fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+            Ok(value)
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(value != 0)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(value != 0)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match value {
+                "" | "0" | "false" => Ok(false),
+                "1" | "true" => Ok(true),
+                _ => Err(de::Error::custom(format!("Invalid boolean {value}"))),
+            }
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(false)
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(false)
+        }
+    }
+
+    deserializer.deserialize_any(BoolVisitor)
+}
+
 pub fn parse_int<F>(item: &str, name: &'static str) -> Result<F, FieldParseError>
 where
     F: FromStr<Err=ParseIntError>,
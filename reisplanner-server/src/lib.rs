@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::routing::get;
+use rbatis::RBatis;
+use tracing::debug;
+
+use reisplanner_algorithm::algorithms::raptor;
+use reisplanner_algorithm::algorithms::raptor::RRoute;
+use reisplanner_algorithm::search::SearchIndex;
+use reisplanner_algorithm::spatial::StopIndex;
+
+pub mod error;
+pub mod params;
+pub mod routes;
+#[cfg(test)]
+mod tests;
+
+/// Everything the request handlers need, loaded once at startup
+pub struct AppState {
+    pub db: RBatis,
+    pub timetable: Arc<HashMap<u32, RRoute>>,
+    pub transfer_times: Arc<HashMap<u32, u32>>,
+    pub search: SearchIndex,
+    /// Stations and stops without a parent station
+    pub stations: StopIndex,
+}
+
+impl AppState {
+    /// Load the timetable, from the cache on disk if `cache` is set, and the search and spatial indices
+    pub async fn load(db: RBatis, cache: bool) -> anyhow::Result<Self> {
+        debug!("Loading timetable...");
+        let timetable = Arc::new(raptor::get_timetable(&db, cache).await?);
+        let transfer_times = Arc::new(raptor::generate_transfer_times(&db).await?);
+        debug!("Building search and spatial indices...");
+        let search = SearchIndex::load(&db).await?;
+        let stations = StopIndex::load_stations(&db).await?;
+        Ok(Self { db, timetable, transfer_times, search, stations })
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/plan", get(routes::plan::plan))
+        .route("/stops", get(routes::stops::search))
+        .route("/stops/{stop_id}/departures", get(routes::stops::departures))
+        .route("/stops/{stop_id}/arrivals", get(routes::stops::arrivals))
+        .route("/trips", get(routes::trips::trip_by_number))
+        .route("/trips/{trip_id}", get(routes::trips::trip))
+        .route("/otp/routers/{router_id}/plan", get(routes::otp::plan))
+        .with_state(Arc::new(state))
+}
@@ -0,0 +1,38 @@
+use std::env;
+
+use rbatis::RBatis;
+use tokio::net::TcpListener;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+use reisplanner_algorithm::database::new_db_connection;
+use reisplanner_server::{router, AppState};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+
+/// Serve the planner over HTTP. The database can be set with `DATABASE_URL`
+/// (e.g. `sqlite://fixture.db`) and the address with `BIND_ADDRESS`.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let log_level = EnvFilter::try_from_default_env()
+        .unwrap_or(EnvFilter::new("error,reisplanner=debug"));
+    tracing_subscriber::fmt().with_env_filter(log_level).init();
+
+    // The timetable cache is shared, so it is only used for the default database
+    let cache = env::var("DATABASE_URL").is_err();
+    let db = match env::var("DATABASE_URL") {
+        Ok(url) => {
+            let db = RBatis::new();
+            db.init(rbdc_sqlite::driver::SqliteDriver {}, &url)?;
+            db
+        }
+        Err(_) => new_db_connection()?,
+    };
+    let state = AppState::load(db, cache).await?;
+
+    let address = env::var("BIND_ADDRESS").unwrap_or(DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).await?;
+    info!("Listening on http://{address}");
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
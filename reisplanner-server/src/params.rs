@@ -0,0 +1,37 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveTime, Timelike, Utc};
+
+use reisplanner_algorithm::export::{today, TIMEZONE};
+
+use crate::error::ApiError;
+
+/// Parse a date as YYYY-MM-DD, defaulting to today in the time zone of the feed
+pub fn parse_date(value: Option<&str>) -> Result<NaiveDate, ApiError> {
+    match value {
+        None => Ok(today()),
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| ApiError::BadRequest(format!("Invalid date {value}, expected YYYY-MM-DD"))),
+    }
+}
+
+/// Parse a time as HH:MM or HH:MM:SS to seconds since midnight, defaulting to now
+pub fn parse_time(value: Option<&str>) -> Result<u32, ApiError> {
+    match value {
+        None => Ok(Utc::now().with_timezone(&TIMEZONE).time().num_seconds_from_midnight()),
+        Some(value) => NaiveTime::parse_from_str(value, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+            .map(|time| time.num_seconds_from_midnight())
+            .map_err(|_| ApiError::BadRequest(format!("Invalid time {value}, expected HH:MM"))),
+    }
+}
+
+/// Parse a comma separated list
+pub fn parse_list<T: FromStr>(value: Option<&str>) -> Result<Vec<T>, ApiError> {
+    let Some(value) = value else { return Ok(Vec::new()) };
+    value.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(|_| ApiError::BadRequest(format!("Invalid value {item}"))))
+        .collect()
+}
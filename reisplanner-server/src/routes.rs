@@ -0,0 +1,4 @@
+pub mod otp;
+pub mod plan;
+pub mod stops;
+pub mod trips;
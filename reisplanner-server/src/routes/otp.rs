@@ -0,0 +1,417 @@
+//! Compatibility layer for the OpenTripPlanner REST `plan` API,
+//! so frontends made for OpenTripPlanner can be used with this planner
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use geo::{Haversine, Length, LineString};
+use rbatis::RBatis;
+use serde::{Deserialize, Serialize};
+
+use reisplanner_algorithm::algorithms::raptor::QueryOptions;
+use reisplanner_algorithm::database::queries::{get_active_services, get_stops};
+use reisplanner_algorithm::export::service_time;
+use reisplanner_algorithm::geometry::{leg_geometry, straight_line};
+use reisplanner_algorithm::result::{journey_results, JourneyResult, Leg, StopInfo};
+use reisplanner_algorithm::spatial::StopIndex;
+use reisplanner_gtfs::gtfs::types::RouteType;
+use reisplanner_gtfs::shapes::encode_polyline;
+
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use crate::params::{parse_date, parse_time};
+use crate::routes::plan::{find_journeys, PlanQuery};
+
+/// Feed id used in OpenTripPlanner ids, e.g. `1:3000`
+const FEED_ID: &str = "1";
+/// Distance in meters around a coordinate in which the nearest station is searched
+const NEAREST_RADIUS: f64 = 2000.0;
+const DEFAULT_ITINERARIES: usize = 3;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpPlanQuery {
+    /// `lat,lon`, a stop id (optionally with feed id, `1:3000`) or `name::place`
+    pub from_place: String,
+    pub to_place: String,
+    /// Date as YYYY-MM-DD or MM-DD-YYYY, defaults to today. Dates without service are outside the service period
+    pub date: Option<String>,
+    /// Time as HH:MM, HH:MM:SS or H:MMam, defaults to now
+    pub time: Option<String>,
+    /// Arrive before `time` instead of departing after it
+    #[serde(default)]
+    pub arrive_by: bool,
+    /// Comma separated modes, e.g. `TRANSIT,WALK` or `RAIL,WALK`
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub wheelchair: bool,
+    pub num_itineraries: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpResponse {
+    pub plan: OtpPlan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<OtpError>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpError {
+    pub id: u32,
+    pub msg: String,
+    pub message: String,
+    pub no_path: bool,
+}
+
+impl OtpError {
+    fn path_not_found() -> Self {
+        Self {
+            id: 404,
+            msg: "No trip found. There may be no transit service within the maximum specified distance or at the specified time, or your start or end point might not be safely accessible.".to_string(),
+            message: "PATH_NOT_FOUND".to_string(),
+            no_path: true,
+        }
+    }
+
+    fn outside_service_period() -> Self {
+        Self {
+            id: 406,
+            msg: "The date specified is outside the service period of the transit data.".to_string(),
+            message: "OUTSIDE_SERVICE_PERIOD".to_string(),
+            no_path: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpPlan {
+    /// Time of the request in milliseconds since the epoch
+    pub date: i64,
+    pub from: OtpPlace,
+    pub to: OtpPlace,
+    pub itineraries: Vec<Itinerary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpPlace {
+    pub name: String,
+    pub stop_id: Option<String>,
+    pub stop_code: Option<String>,
+    pub platform_code: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub arrival: Option<i64>,
+    pub departure: Option<i64>,
+    pub vertex_type: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Itinerary {
+    /// Durations in seconds, times in milliseconds since the epoch
+    pub duration: i64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub walk_time: i64,
+    pub transit_time: i64,
+    pub waiting_time: i64,
+    /// Distance in meters
+    pub walk_distance: f64,
+    pub transfers: usize,
+    pub legs: Vec<OtpLeg>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpLeg {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub duration: f64,
+    pub distance: f64,
+    pub mode: String,
+    pub transit_leg: bool,
+    pub real_time: bool,
+    pub interline_with_previous_leg: bool,
+    pub route: String,
+    pub route_id: Option<String>,
+    pub route_short_name: Option<String>,
+    pub route_long_name: Option<String>,
+    pub route_type: Option<u8>,
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>,
+    pub agency_id: Option<String>,
+    pub trip_id: Option<String>,
+    pub trip_short_name: Option<String>,
+    pub headsign: Option<String>,
+    pub from: OtpPlace,
+    pub to: OtpPlace,
+    pub leg_geometry: EncodedPolyline,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncodedPolyline {
+    pub points: String,
+    /// Number of points
+    pub length: usize,
+}
+
+/// OpenTripPlanner mode of a route type
+pub fn otp_mode(route_type: RouteType) -> &'static str {
+    match route_type {
+        RouteType::Tram => "TRAM",
+        RouteType::Metro => "SUBWAY",
+        RouteType::Train => "RAIL",
+        RouteType::Bus => "BUS",
+        RouteType::Ferry => "FERRY",
+        RouteType::CableTram => "CABLE_CAR",
+        RouteType::Lift => "GONDOLA",
+        RouteType::Funicular => "FUNICULAR",
+        RouteType::TrolleyBus => "TROLLEYBUS",
+        RouteType::MonoRail => "MONORAIL",
+    }
+}
+
+/// Route types allowed by an OpenTripPlanner `mode` parameter, `None` if all are allowed
+fn allowed_route_types(mode: Option<&str>) -> ApiResult<Option<HashSet<RouteType>>> {
+    let Some(mode) = mode else { return Ok(None) };
+    let all = [
+        RouteType::Tram, RouteType::Metro, RouteType::Train, RouteType::Bus, RouteType::Ferry,
+        RouteType::CableTram, RouteType::Lift, RouteType::Funicular, RouteType::TrolleyBus, RouteType::MonoRail,
+    ];
+    let mut allowed = HashSet::new();
+    for mode in mode.split(',').map(|m| m.trim().to_uppercase()) {
+        match mode.as_str() {
+            "TRANSIT" => return Ok(None),
+            "WALK" | "" => {}
+            _ => {
+                let route_type = all.iter()
+                    .find(|&&t| otp_mode(t) == mode)
+                    .ok_or(ApiError::BadRequest(format!("Unsupported mode {mode}")))?;
+                allowed.insert(*route_type);
+            }
+        }
+    }
+    Ok(Some(allowed))
+}
+
+/// Parse a date as YYYY-MM-DD or MM-DD-YYYY
+fn parse_otp_date(value: Option<&str>) -> ApiResult<NaiveDate> {
+    match value.map(|v| NaiveDate::parse_from_str(v, "%m-%d-%Y")) {
+        Some(Ok(date)) => Ok(date),
+        _ => parse_date(value),
+    }
+}
+
+/// Parse a time as HH:MM, HH:MM:SS, H:MMam or H:MM pm to seconds since midnight
+fn parse_otp_time(value: Option<&str>) -> ApiResult<u32> {
+    let Some(value) = value else {
+        return parse_time(None);
+    };
+    let normalised = value.trim().to_lowercase().replace(' ', "");
+    ["%H:%M:%S", "%H:%M", "%I:%M%P", "%I:%M:%S%P"].iter()
+        .find_map(|format| NaiveTime::parse_from_str(&normalised, format).ok())
+        .map(|time| time.num_seconds_from_midnight())
+        .ok_or(ApiError::BadRequest(format!("Invalid time {value}")))
+}
+
+/// Resolve a place to a station id, using the nearest station for coordinates
+fn resolve_place(place: &str, stations: &StopIndex) -> ApiResult<u32> {
+    // `name::place`
+    let place = place.rsplit("::").next().unwrap_or(place).trim();
+    if let Some((lat, lon)) = place.split_once(',') {
+        if let (Ok(lat), Ok(lon)) = (lat.trim().parse(), lon.trim().parse()) {
+            return nearest_station(lat, lon, stations);
+        }
+    }
+    // Stop id, optionally prefixed by the feed id
+    let id = place.rsplit(':').next().unwrap_or(place);
+    id.parse().map_err(|_| ApiError::BadRequest(format!("Invalid place {place}")))
+}
+
+fn nearest_station(lat: f64, lon: f64, stations: &StopIndex) -> ApiResult<u32> {
+    stations.within_radius(lat, lon, NEAREST_RADIUS)
+        .first()
+        .map(|nearest| nearest.stop.stop_id)
+        .ok_or(ApiError::NotFound(format!("No station near {lat},{lon}")))
+}
+
+/// Plan journeys, `GET /otp/routers/{router_id}/plan`
+pub async fn plan(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<OtpPlanQuery>,
+) -> ApiResult<Json<OtpResponse>> {
+    let from = resolve_place(&query.from_place, &state.stations)?;
+    let to = resolve_place(&query.to_place, &state.stations)?;
+    let date = parse_otp_date(query.date.as_deref())?;
+    let time = parse_otp_time(query.time.as_deref())?;
+    let count = query.num_itineraries.unwrap_or(DEFAULT_ITINERARIES);
+    let options = QueryOptions {
+        allowed_route_types: allowed_route_types(query.mode.as_deref())?,
+        wheelchair: query.wheelchair,
+        ..Default::default()
+    };
+
+    let stops = get_stops(&[from, to], &state.db).await?;
+    let place = |stop_id: u32| -> ApiResult<OtpPlace> {
+        let stop = stops.get(&stop_id)
+            .ok_or(ApiError::NotFound(format!("Stop {stop_id} not found")))?;
+        Ok(OtpPlace {
+            name: stop.stop_name.clone(),
+            stop_id: Some(format!("{FEED_ID}:{stop_id}")),
+            stop_code: None,
+            platform_code: stop.platform_code.clone(),
+            lat: stop.stop_lat,
+            lon: stop.stop_lon,
+            arrival: None,
+            departure: None,
+            vertex_type: "TRANSIT".to_string(),
+        })
+    };
+    let response = |itineraries: Vec<Itinerary>, error| -> ApiResult<Json<OtpResponse>> {
+        Ok(Json(OtpResponse {
+            plan: OtpPlan {
+                date: service_time(date, time)?.timestamp_millis(),
+                from: place(from)?,
+                to: place(to)?,
+                itineraries,
+            },
+            error,
+        }))
+    };
+
+    if get_active_services(date, &state.db).await?.is_empty() {
+        return response(Vec::new(), Some(OtpError::outside_service_period()));
+    }
+
+    let plan_query = PlanQuery { from, to, arrive_by: query.arrive_by, count: Some(count), ..Default::default() };
+    let journeys = find_journeys(&state, &plan_query, &options, date, time, &[]).await?;
+    let results = journey_results(&journeys, &state.db).await?;
+
+    let mut itineraries = Vec::with_capacity(results.len());
+    for result in &results {
+        itineraries.push(itinerary(result, date, &state.db).await?);
+    }
+    let error = itineraries.is_empty().then(OtpError::path_not_found);
+    response(itineraries, error)
+}
+
+fn stop_place(stop: &StopInfo, arrival: Option<i64>, departure: Option<i64>) -> OtpPlace {
+    OtpPlace {
+        name: stop.name.clone(),
+        stop_id: Some(format!("{FEED_ID}:{}", stop.stop_id)),
+        stop_code: None,
+        platform_code: stop.platform_code.clone(),
+        lat: stop.lat,
+        lon: stop.lon,
+        arrival,
+        departure,
+        vertex_type: "TRANSIT".to_string(),
+    }
+}
+
+fn encoded_polyline(line: &LineString) -> anyhow::Result<EncodedPolyline> {
+    Ok(EncodedPolyline {
+        points: encode_polyline(line.0.iter().copied())?,
+        length: line.0.len(),
+    })
+}
+
+/// Convert a journey to an itinerary, transfers become walking legs
+async fn itinerary(journey: &JourneyResult, date: NaiveDate, db: &RBatis) -> anyhow::Result<Itinerary> {
+    let millis = |seconds: u32| -> anyhow::Result<i64> {
+        Ok(service_time(date, seconds)?.timestamp_millis())
+    };
+
+    let mut legs = Vec::new();
+    let mut time = journey.departure.unwrap_or_default();
+    let (mut walk_time, mut transit_time, mut walk_distance) = (0, 0, 0.0);
+    for leg in &journey.legs {
+        match leg {
+            Leg::Vehicle(vehicle) => {
+                let line = leg_geometry(vehicle, db).await?;
+                let (start, end) = (millis(vehicle.departure)?, millis(vehicle.arrival)?);
+                transit_time += (vehicle.arrival - vehicle.departure) as i64;
+                legs.push(OtpLeg {
+                    start_time: start,
+                    end_time: end,
+                    duration: (vehicle.arrival - vehicle.departure) as f64,
+                    distance: line.length::<Haversine>(),
+                    mode: otp_mode(vehicle.route_type).to_string(),
+                    transit_leg: true,
+                    real_time: false,
+                    interline_with_previous_leg: false,
+                    route: vehicle.route_short_name.clone(),
+                    route_id: Some(format!("{FEED_ID}:{}", vehicle.route_id)),
+                    route_short_name: Some(vehicle.route_short_name.clone()),
+                    route_long_name: Some(vehicle.route_long_name.clone()),
+                    route_type: Some(vehicle.route_type as u8),
+                    route_color: vehicle.route_color.clone(),
+                    route_text_color: vehicle.route_text_color.clone(),
+                    agency_id: Some(format!("{FEED_ID}:{}", vehicle.agency_id)),
+                    trip_id: Some(format!("{FEED_ID}:{}", vehicle.trip_id)),
+                    trip_short_name: vehicle.trip_short_name.clone(),
+                    headsign: Some(vehicle.headsign.clone()),
+                    from: stop_place(&vehicle.from, None, Some(start)),
+                    to: stop_place(&vehicle.to, Some(end), None),
+                    leg_geometry: encoded_polyline(&line)?,
+                });
+                time = vehicle.arrival;
+            }
+            Leg::Transfer(transfer) => {
+                let line = straight_line(&transfer.from, &transfer.to);
+                let distance = line.length::<Haversine>();
+                let (start, end) = (millis(time)?, millis(time + transfer.duration)?);
+                walk_time += transfer.duration as i64;
+                walk_distance += distance;
+                legs.push(OtpLeg {
+                    start_time: start,
+                    end_time: end,
+                    duration: transfer.duration as f64,
+                    distance,
+                    mode: "WALK".to_string(),
+                    transit_leg: false,
+                    real_time: false,
+                    interline_with_previous_leg: false,
+                    route: String::new(),
+                    route_id: None,
+                    route_short_name: None,
+                    route_long_name: None,
+                    route_type: None,
+                    route_color: None,
+                    route_text_color: None,
+                    agency_id: None,
+                    trip_id: None,
+                    trip_short_name: None,
+                    headsign: None,
+                    from: stop_place(&transfer.from, None, Some(start)),
+                    to: stop_place(&transfer.to, Some(end), None),
+                    leg_geometry: encoded_polyline(&line)?,
+                });
+                time += transfer.duration;
+            }
+            Leg::Via(_) => {}
+        }
+    }
+
+    let departure = journey.departure.unwrap_or_default();
+    let arrival = journey.arrival.unwrap_or(departure);
+    let duration = (arrival - departure) as i64;
+    Ok(Itinerary {
+        duration,
+        start_time: millis(departure)?,
+        end_time: millis(arrival)?,
+        walk_time,
+        transit_time,
+        waiting_time: (duration - walk_time - transit_time).max(0),
+        walk_distance,
+        transfers: journey.transfers,
+        legs,
+    })
+}
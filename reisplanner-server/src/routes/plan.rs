@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use reisplanner_algorithm::algorithms::raptor::{run_raptor_via, QueryOptions};
+use reisplanner_algorithm::algorithms::raptor::alternatives::{run_raptor_alternatives, run_raptor_arrive_by, AlternativesOptions};
+use reisplanner_algorithm::algorithms::raptor::options::{BicycleOptions, ViaStop};
+use reisplanner_algorithm::database::queries::get_active_services;
+use reisplanner_algorithm::reliability::annotate_reliability;
+use reisplanner_algorithm::result::{journey_results, JourneyResult};
+use reisplanner_algorithm::types::Journey;
+use reisplanner_gtfs::gtfs::types::RouteType;
+
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use crate::params::{parse_date, parse_list, parse_time};
+
+/// Maximum number of journeys that can be requested
+const MAX_COUNT: usize = 10;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PlanQuery {
+    /// Departure station (parent stop_id without the `stoparea:` prefix)
+    pub from: u32,
+    /// Arrival station
+    pub to: u32,
+    /// Date as YYYY-MM-DD, defaults to today
+    pub date: Option<String>,
+    /// Departure time as HH:MM, defaults to now
+    pub time: Option<String>,
+    /// Arrive before `time` instead of departing after it
+    #[serde(default)]
+    pub arrive_by: bool,
+    /// Number of journeys, not with `via`
+    pub count: Option<usize>,
+    /// Comma separated route types that may be used, e.g. `train,bus`
+    pub modes: Option<String>,
+    /// Comma separated route types that may not be used
+    pub exclude_modes: Option<String>,
+    /// Comma separated operators that may be used
+    pub agencies: Option<String>,
+    /// Comma separated operators that may not be used
+    pub exclude_agencies: Option<String>,
+    /// Comma separated stations the journey may not use
+    pub avoid: Option<String>,
+    /// Comma separated stations the journey should visit, in order
+    pub via: Option<String>,
+    /// Minimum time in seconds to stay at every via station
+    pub via_stay: Option<u32>,
+    #[serde(default)]
+    pub wheelchair: bool,
+    #[serde(default)]
+    pub bicycle: bool,
+    /// Extra time in seconds added to every transfer
+    pub transfer_buffer: Option<u32>,
+    /// Maximum total transfer time in seconds
+    pub max_transfer_time: Option<u32>,
+}
+
+impl PlanQuery {
+    /// Options for a query on `date`
+    pub fn query_options(&self, date: NaiveDate) -> Result<QueryOptions, ApiError> {
+        let modes: Vec<RouteType> = parse_list(self.modes.as_deref())?;
+        let agencies: Vec<String> = parse_list(self.agencies.as_deref())?;
+        Ok(QueryOptions {
+            allowed_route_types: (!modes.is_empty()).then(|| modes.into_iter().collect()),
+            excluded_route_types: parse_list(self.exclude_modes.as_deref())?.into_iter().collect(),
+            allowed_agencies: (!agencies.is_empty()).then(|| agencies.into_iter().collect()),
+            excluded_agencies: parse_list(self.exclude_agencies.as_deref())?.into_iter().collect(),
+            wheelchair: self.wheelchair,
+            bicycle: self.bicycle.then(|| BicycleOptions::ns_rush_hours(date)),
+            avoided_stops: parse_list(self.avoid.as_deref())?.into_iter().collect::<HashSet<_>>(),
+            transfer_buffer: self.transfer_buffer.unwrap_or_default(),
+            max_transfer_time: self.max_transfer_time,
+            ..Default::default()
+        })
+    }
+}
+
+/// Plan journeys, `GET /plan?from=..&to=..`
+pub async fn plan(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PlanQuery>,
+) -> ApiResult<Json<Vec<JourneyResult>>> {
+    let date = parse_date(query.date.as_deref())?;
+    let options = query.query_options(date)?;
+    let time = parse_time(query.time.as_deref())?;
+    let via: Vec<u32> = parse_list(query.via.as_deref())?;
+
+    let journeys = find_journeys(&state, &query, &options, date, time, &via).await?;
+    Ok(Json(journey_results(&journeys, &state.db).await?))
+}
+
+/// Find the journeys for a query on the trips that run on `date`, annotated with their reliability
+pub async fn find_journeys(
+    state: &AppState,
+    query: &PlanQuery,
+    options: &QueryOptions,
+    date: NaiveDate,
+    time: u32,
+    via: &[u32],
+) -> ApiResult<Vec<Journey>> {
+    if !via.is_empty() && query.count.is_some() {
+        return Err(ApiError::BadRequest("count can not be combined with via".to_string()));
+    }
+    if !via.is_empty() && query.arrive_by {
+        return Err(ApiError::BadRequest("arrive_by can not be combined with via".to_string()));
+    }
+    let options = &QueryOptions { services: Some(get_active_services(date, &state.db).await?), ..options.clone() };
+    let alternatives = AlternativesOptions {
+        count: query.count.unwrap_or(AlternativesOptions::default().count).clamp(1, MAX_COUNT),
+        ..Default::default()
+    };
+
+    let mut journeys = if !via.is_empty() {
+        let via: Vec<ViaStop> = via.iter()
+            .map(|&stop_id| ViaStop { stop_id, min_stay: query.via_stay.unwrap_or_default() })
+            .collect();
+        run_raptor_via(
+            query.from, query.to, &via, time, &state.timetable, &state.transfer_times,
+            options, &state.db,
+        ).await?
+            .map(Journey::from)
+            .into_iter()
+            .collect()
+    } else if query.arrive_by {
+        run_raptor_arrive_by(
+            query.from, query.to, time, &state.timetable, &state.transfer_times,
+            options, &alternatives, &state.db,
+        ).await?
+    } else {
+        run_raptor_alternatives(
+            query.from, query.to, time, &state.timetable, &state.transfer_times,
+            options, &alternatives, &state.db,
+        ).await?
+    };
+
+    for journey in &mut journeys {
+        annotate_reliability(journey, &state.db).await?;
+    }
+    Ok(journeys)
+}
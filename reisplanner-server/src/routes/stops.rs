@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use serde::Deserialize;
+
+use reisplanner_algorithm::board::{get_arrivals, get_departures, Arrival, Departure};
+use reisplanner_algorithm::search::SearchResult;
+
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use crate::params::{parse_date, parse_time};
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+/// Search stations and stops by name or code, `GET /stops?q=..`.
+/// Suitable for autocomplete, as the last word may be incomplete.
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Json<Vec<SearchResult>>> {
+    if query.q.trim().is_empty() {
+        return Err(ApiError::BadRequest("Empty search query".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    Ok(Json(state.search.search(&query.q, limit)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BoardQuery {
+    /// Date as YYYY-MM-DD, defaults to today
+    pub date: Option<String>,
+    /// Time as HH:MM, defaults to now
+    pub time: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Departure board of a stop or station, `GET /stops/{stop_id}/departures`
+pub async fn departures(
+    State(state): State<Arc<AppState>>,
+    Path(stop_id): Path<u32>,
+    Query(query): Query<BoardQuery>,
+) -> ApiResult<Json<Vec<Departure>>> {
+    let date = parse_date(query.date.as_deref())?;
+    let time = parse_time(query.time.as_deref())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    Ok(Json(get_departures(stop_id, date, time, limit, &state.db).await?))
+}
+
+/// Arrival board of a stop or station, `GET /stops/{stop_id}/arrivals`
+pub async fn arrivals(
+    State(state): State<Arc<AppState>>,
+    Path(stop_id): Path<u32>,
+    Query(query): Query<BoardQuery>,
+) -> ApiResult<Json<Vec<Arrival>>> {
+    let date = parse_date(query.date.as_deref())?;
+    let time = parse_time(query.time.as_deref())?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    Ok(Json(get_arrivals(stop_id, date, time, limit, &state.db).await?))
+}
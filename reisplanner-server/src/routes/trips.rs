@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use serde::Deserialize;
+
+use reisplanner_algorithm::trip_details::{get_trip_details, get_trip_details_by_number, TripDetails};
+
+use crate::AppState;
+use crate::error::{ApiError, ApiResult};
+use crate::params::parse_date;
+
+#[derive(Debug, Deserialize)]
+pub struct TripQuery {
+    /// Service date as YYYY-MM-DD, defaults to today
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripNumberQuery {
+    /// Trip short name, e.g. the train number
+    pub number: String,
+    pub date: Option<String>,
+}
+
+/// Details of a trip, `GET /trips/{trip_id}`
+pub async fn trip(
+    State(state): State<Arc<AppState>>,
+    Path(trip_id): Path<u32>,
+    Query(query): Query<TripQuery>,
+) -> ApiResult<Json<TripDetails>> {
+    let date = parse_date(query.date.as_deref())?;
+    get_trip_details(trip_id, date, &state.db).await?
+        .map(Json)
+        .ok_or(ApiError::NotFound(format!("Trip {trip_id} not found")))
+}
+
+/// Details of a trip by its number, `GET /trips?number=..`
+pub async fn trip_by_number(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TripNumberQuery>,
+) -> ApiResult<Json<TripDetails>> {
+    let date = parse_date(query.date.as_deref())?;
+    get_trip_details_by_number(&query.number, date, &state.db).await?
+        .map(Json)
+        .ok_or(ApiError::NotFound(format!("Trip {} does not run on {date}", query.number)))
+}
@@ -0,0 +1,164 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use chrono::NaiveDate;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use reisplanner_algorithm::export::{service_time, today};
+use reisplanner_algorithm::fixture;
+use reisplanner_gtfs::utils::TimeTuple;
+
+use crate::{router, AppState};
+
+async fn app(date: NaiveDate) -> anyhow::Result<Router> {
+    let db = fixture::fixture_db(date).await?;
+    Ok(router(AppState::load(db, false).await?))
+}
+
+async fn get(app: &Router, uri: &str) -> anyhow::Result<(StatusCode, Value)> {
+    let response = app.clone()
+        .oneshot(Request::get(uri).body(Body::empty())?)
+        .await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+    Ok((status, serde_json::from_slice(&body)?))
+}
+
+#[tokio::test]
+async fn plan_endpoint() -> anyhow::Result<()> {
+    let app = app(today()).await?;
+
+    let uri = format!("/plan?from={}&to={}&time=09:00&count=1", fixture::DEN_HAAG, fixture::AMSTERDAM);
+    let (status, journeys) = get(&app, &uri).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(journeys.as_array().map(Vec::len), Some(1));
+    assert_eq!(journeys[0]["departure"], 9 * 3600 + 20 * 60);
+    assert_eq!(journeys[0]["arrival"], 10 * 3600 + 27 * 60);
+    assert_eq!(journeys[0]["legs"][0]["trip_short_name"], "2100");
+
+    // Only trains run in the fixture
+    let (status, journeys) = get(&app, &format!("{uri}&modes=bus")).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(journeys, Value::Array(Vec::new()));
+
+    let (status, error) = get(&app, &format!("{uri}&modes=zeppelin")).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["error"], "Invalid value zeppelin");
+
+    // The fixture only has service today
+    let tomorrow = today().succ_opt().unwrap();
+    let (_, journeys) = get(&app, &format!("{uri}&date={tomorrow}")).await?;
+    assert_eq!(journeys, Value::Array(Vec::new()));
+
+    let uri = format!("/plan?from={}&to={}&time=10:30&arrive_by=true", fixture::DEN_HAAG, fixture::AMSTERDAM);
+    let (_, journeys) = get(&app, &uri).await?;
+    assert_eq!(journeys[0]["legs"][0]["trip_short_name"], "2100");
+
+    // Staying 10 minutes in Utrecht misses the continuation of trip 100
+    let uri = format!("/plan?from={}&to={}&time=09:00&via={}", fixture::DEN_HAAG, fixture::AMSTERDAM, fixture::UTRECHT);
+    let (_, journeys) = get(&app, &uri).await?;
+    assert_eq!(journeys[0]["arrival"], 10 * 3600 + 27 * 60);
+    let (_, journeys) = get(&app, &format!("{uri}&via_stay=600")).await?;
+    assert_eq!(journeys[0]["arrival"], 10 * 3600 + 57 * 60);
+    let (status, _) = get(&app, &format!("{uri}&count=2")).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn stop_endpoints() -> anyhow::Result<()> {
+    let today = today();
+    let app = app(today).await?;
+
+    let (status, stops) = get(&app, "/stops?q=utrecht").await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stops[0]["stop_id"], fixture::UTRECHT);
+    assert_eq!(stops[0]["name"], "Utrecht Centraal");
+
+    let uri = format!("/stops/{}/departures?date={today}&time=10:00", fixture::UTRECHT);
+    let (status, departures) = get(&app, &uri).await?;
+    assert_eq!(status, StatusCode::OK);
+    let trips: Vec<&Value> = departures.as_array().unwrap().iter().map(|d| &d["trip_id"]).collect();
+    assert_eq!(trips, [100, 101, 102]);
+    assert_eq!(departures[2]["cancelled"], true);
+
+    let uri = format!("/stops/{}/arrivals?date={today}&time=09:00", fixture::UTRECHT);
+    let (_, arrivals) = get(&app, &uri).await?;
+    assert_eq!(arrivals.as_array().map(Vec::len), Some(2));
+
+    let (status, _) = get(&app, "/stops/1000/departures?time=25:99").await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn trip_endpoints() -> anyhow::Result<()> {
+    let today = today();
+    let app = app(today).await?;
+
+    let (status, trip) = get(&app, &format!("/trips?number=4800&date={today}")).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(trip["trip_id"], 101);
+
+    let (status, trip) = get(&app, &format!("/trips/100?date={today}")).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(trip["stops"].as_array().map(Vec::len), Some(3));
+
+    let (status, error) = get(&app, &format!("/trips?number=1234&date={today}")).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(error["error"].as_str().unwrap().contains("1234"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn otp_plan_endpoint() -> anyhow::Result<()> {
+    let today = today();
+    let tomorrow = today.succ_opt().unwrap();
+    let tomorrow_app = app(tomorrow).await?;
+    let app = app(today).await?;
+
+    // From a stop id with feed id to coordinates near Amsterdam Centraal
+    let uri = format!("/otp/routers/default/plan?fromPlace=Den%20Haag::1:3000&toPlace=52.379,4.900\
+        &date={}&time=9:00am&mode=RAIL,WALK", today.format("%m-%d-%Y"));
+    let (status, response) = get(&app, &uri).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["plan"]["to"]["name"], "Amsterdam Centraal");
+    let itinerary = &response["plan"]["itineraries"][0];
+    let start = service_time(today, TimeTuple(9, 20, 0).into())?;
+    assert_eq!(itinerary["startTime"], start.timestamp_millis());
+    assert_eq!(itinerary["duration"], 67 * 60);
+    let leg = &itinerary["legs"][0];
+    assert_eq!(leg["mode"], "RAIL");
+    assert_eq!(leg["tripId"], "1:100");
+    assert_eq!(leg["from"]["platformCode"], "1");
+    // The shape of trip 100 via Leiden and Utrecht
+    assert_eq!(leg["legGeometry"]["length"], 4);
+
+    // Arrive by 10:30, but not by 10:20
+    let uri = "/otp/routers/default/plan?fromPlace=1:3000&toPlace=1:2000&time=10:30&arriveBy=true";
+    let (_, response) = get(&app, uri).await?;
+    assert_eq!(response["plan"]["itineraries"][0]["legs"][0]["tripShortName"], "2100");
+    let (_, response) = get(&app, &uri.replace("10:30", "10:20")).await?;
+    assert_eq!(response["plan"]["itineraries"], Value::Array(Vec::new()));
+    assert_eq!(response["error"]["message"], "PATH_NOT_FOUND");
+
+    let (status, _) = get(&app, &uri.replace("time=10:30", "mode=HOVERCRAFT")).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The fixture has no service on other dates
+    let uri = "/otp/routers/default/plan?fromPlace=1:3000&toPlace=1:2000&date=07-01-2024&time=09:00";
+    let (status, response) = get(&app, uri).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["plan"]["itineraries"], Value::Array(Vec::new()));
+    assert_eq!(response["error"]["message"], "OUTSIDE_SERVICE_PERIOD");
+    assert_eq!(response["plan"]["from"]["name"], "Den Haag Centraal");
+
+    // Journeys on other dates with service are planned on their own trips
+    let (_, response) = get(&tomorrow_app, &uri.replace("07-01-2024", &tomorrow.to_string())).await?;
+    assert_eq!(response["plan"]["itineraries"][0]["legs"][0]["tripId"], "1:100");
+    let (_, response) = get(&tomorrow_app, &uri.replace("07-01-2024", &today.to_string())).await?;
+    assert_eq!(response["error"]["message"], "OUTSIDE_SERVICE_PERIOD");
+    Ok(())
+}